@@ -1,15 +1,22 @@
-use std::any::type_name;
+use std::any::{TypeId, type_name};
 
 use ordered_float::OrderedFloat;
 
 use crate::{
     components::{
+        age_component::AgeComponent,
         agent_component::AgentComponent,
-        all::{CreatureComponent, HerbivorousComponent, PlantComponent},
+        all::{
+            CorpseComponent, CreatureComponent, DigestionComponent, PlantComponent,
+            SleepingComponent, SpeciesComponent, WaterComponent,
+        },
+        body_component::BodyComponent,
+        genome_component::GenomeComponent,
     },
-    configuration::Config,
-    ecs::{Component, Ecs, EntityInfo, RESERVED_ENTITY_ID, Update},
+    configuration::{Config, DietConfig},
+    ecs::{Component, Ecs, EntityInfo, RESERVED_ENTITY_ID, Update, iter_components, to_ctype},
     goap::{Action, ActionResult, Condition, Effect, Modifier, Operator, Symbol, Value},
+    systems::utils,
 };
 
 pub fn get_comp_or_error<'e, A, C>(ecs: &'e mut Ecs, info: &EntityInfo) -> Result<&'e mut C, String>
@@ -28,11 +35,14 @@ where
 
 pub struct EatPlantAction {
     preconditions: [Condition; 1],
-    effects: [Effect; 2],
+    effects: [Effect; 3],
+    diet: DietConfig,
 }
 impl EatPlantAction {
-    pub fn new(config: &Config) -> Self {
-        let estimated_gain = config.plant.max_size as f32 / 2.0 * config.plant.energy_per_size_unit;
+    pub fn new(config: &Config, diet: &DietConfig) -> Self {
+        let estimated_gain = config.plant.max_size as f32 / 2.0
+            * config.plant.energy_per_size_unit
+            * diet.energy_yield;
         Self {
             preconditions: [Condition::new(
                 Symbol::IsNearPlant,
@@ -45,8 +55,14 @@ impl EatPlantAction {
                     Modifier::Increment,
                     Value::F32(OrderedFloat(estimated_gain)),
                 ),
+                Effect::clamp(
+                    Symbol::Energy,
+                    Value::F32(OrderedFloat(0.0)),
+                    Value::F32(OrderedFloat(config.creature.max_energy)),
+                ),
                 Effect::new(Symbol::IsNearPlant, Modifier::SetValue, Value::Bool(false)),
             ],
+            diet: *diet,
         }
     }
 }
@@ -76,14 +92,15 @@ impl Action for EatPlantAction {
             let plant = get_comp_or_error::<EatPlantAction, PlantComponent>(ecs, &p_info)?.clone();
 
             // Eat the plant's seeds
-            let herbivorous = ecs.component_mut::<HerbivorousComponent>(info).unwrap();
-            herbivorous
+            let digestion = get_comp_or_error::<EatPlantAction, DigestionComponent>(ecs, info)?;
+            digestion
                 .seeds
-                .push_back((plant.nb_seeds, config.creature.herbivorous_ticks_to_digest));
+                .push_back((plant.nb_seeds, config.creature.ticks_to_digest));
 
             // Increase energy
             let creature = get_comp_or_error::<EatPlantAction, CreatureComponent>(ecs, info)?;
-            creature.energy += (plant.size as f32) * config.plant.energy_per_size_unit;
+            creature.energy +=
+                (plant.size as f32) * config.plant.energy_per_size_unit * self.diet.energy_yield;
             if creature.energy > config.creature.max_energy {
                 creature.energy = config.creature.max_energy;
             }
@@ -101,15 +118,43 @@ impl Action for EatPlantAction {
     fn description(&self) -> String {
         String::from("eat plant")
     }
+
+    fn cost_factor(&self) -> f64 {
+        1.0 / self.diet.preference
+    }
+}
+
+/// Eat a corpse until sated, leaving what remains of it (if anything)
+fn eat_corpse<A: Action>(
+    ecs: &mut Ecs,
+    info: &EntityInfo,
+    c_info: &EntityInfo,
+    diet: &DietConfig,
+    config: &Config,
+) -> Result<(), String> {
+    let corpse_energy = get_comp_or_error::<A, CorpseComponent>(ecs, c_info)?.energy;
+    let creature = get_comp_or_error::<A, CreatureComponent>(ecs, info)?;
+    let appetite = (config.creature.max_energy - creature.energy).max(0.0) / diet.energy_yield;
+    let eaten = corpse_energy.min(appetite);
+    creature.energy = (creature.energy + eaten * diet.energy_yield).min(config.creature.max_energy);
+
+    let corpse = get_comp_or_error::<A, CorpseComponent>(ecs, c_info)?;
+    corpse.energy -= eaten;
+    if corpse.energy <= 0.0 {
+        ecs.apply(vec![Update::DeleteEntity(*c_info)]);
+    }
+    Ok(())
 }
 
 pub struct EatCorpseAction {
     preconditions: [Condition; 1],
-    effects: [Effect; 2],
+    effects: [Effect; 3],
+    diet: DietConfig,
 }
 impl EatCorpseAction {
-    pub fn new(config: &Config) -> Self {
-        let estimated_gain = config.creature.corpse_energy;
+    pub fn new(config: &Config, diet: &DietConfig) -> Self {
+        let estimated_gain =
+            config.corpse_energy(config.max_creature_size(), 0.0) * diet.energy_yield;
         Self {
             preconditions: [Condition::new(
                 Symbol::IsNearCorpse,
@@ -122,8 +167,14 @@ impl EatCorpseAction {
                     Modifier::Increment,
                     Value::F32(OrderedFloat(estimated_gain)),
                 ),
+                Effect::clamp(
+                    Symbol::Energy,
+                    Value::F32(OrderedFloat(0.0)),
+                    Value::F32(OrderedFloat(config.creature.max_energy)),
+                ),
                 Effect::new(Symbol::IsNearCorpse, Modifier::SetValue, Value::Bool(false)),
             ],
+            diet: *diet,
         }
     }
 }
@@ -149,16 +200,7 @@ impl Action for EatCorpseAction {
 
         // Check if the target corpse still exists
         if let Some(c_info) = ecs.get_entity_info(corpse_entity) {
-            // Increase energy
-            let creature = get_comp_or_error::<EatCorpseAction, CreatureComponent>(ecs, info)?;
-            creature.energy += config.creature.corpse_energy;
-            if creature.energy > config.creature.max_energy {
-                creature.energy = config.creature.max_energy;
-            }
-
-            // Delete the corpse
-            ecs.apply(vec![Update::DeleteEntity(c_info)]);
-
+            eat_corpse::<EatCorpseAction>(ecs, info, &c_info, &self.diet, config)?;
             Ok(ActionResult::Success)
         } else {
             // Corpse not found, maybe it was already eaten by someone else
@@ -169,18 +211,128 @@ impl Action for EatCorpseAction {
     fn description(&self) -> String {
         String::from("eat corpse")
     }
+
+    fn cost_factor(&self) -> f64 {
+        1.0 / self.diet.preference
+    }
 }
 
-pub struct EatHerbivorousAction {
+/// Fight the prey until it dies, taking damage from the prey fighting back.
+/// The prey is killed when its health reaches zero (its corpse is created by the death system).
+pub struct AttackAction {
     preconditions: [Condition; 1],
     effects: [Effect; 2],
 }
-impl EatHerbivorousAction {
-    pub fn new(config: &Config) -> Self {
-        let estimated_gain = config.creature.corpse_energy;
+impl AttackAction {
+    pub fn new() -> Self {
+        Self {
+            preconditions: [Condition::new(
+                Symbol::IsNearPrey,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+            effects: [
+                Effect::new(Symbol::HasKilledPrey, Modifier::SetValue, Value::Bool(true)),
+                Effect::new(Symbol::IsNearPrey, Modifier::SetValue, Value::Bool(false)),
+            ],
+        }
+    }
+}
+impl Action for AttackAction {
+    fn preconditions(&self) -> &[Condition] {
+        &self.preconditions
+    }
+
+    fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    fn perform(
+        &self,
+        ecs: &mut Ecs,
+        info: &EntityInfo,
+        config: &Config,
+    ) -> Result<ActionResult, String> {
+        let prey_entity =
+            get_comp_or_error::<AttackAction, AgentComponent>(ecs, info)?.target_entity;
+        let body = *get_comp_or_error::<AttackAction, BodyComponent>(ecs, info)?;
+
+        let Some(p_info) = ecs.get_entity_info(prey_entity) else {
+            // The prey is dead: target the corpse of the kill (if the kill is ours)
+            let corpse = iter_components!(ecs, (BodyComponent), (CorpseComponent))
+                .find(|(corpse, _)| {
+                    corpse.creature == Some(prey_entity) && corpse.killer == Some(info.entity)
+                })
+                .map(|(_, c_info)| c_info.entity);
+            let agent = get_comp_or_error::<AttackAction, AgentComponent>(ecs, info)?;
+            return Ok(if let Some(corpse) = corpse {
+                agent.target_entity = corpse;
+                ActionResult::Success
+            } else {
+                agent.target_entity = RESERVED_ENTITY_ID;
+                ActionResult::Failure
+            });
+        };
+
+        // The prey must still be in contact (it may have fled)
+        let Some(prey_body) = ecs.component::<BodyComponent>(&p_info).copied() else {
+            return Ok(ActionResult::Failure);
+        };
+        if !body.almost_collides(&prey_body, config.collision.contact_center_2_center_factor) {
+            return Ok(ActionResult::Failure);
+        }
+
+        // The bigger the creature, the more damage it deals
+        let size_ratio = (body.w() / prey_body.w()) as f32;
+        // A sleeping prey is more vulnerable, and does not fight back
+        let prey_sleeping = ecs.has_component(p_info.arch_index, &to_ctype!(SleepingComponent));
+        let prey = get_comp_or_error::<AttackAction, CreatureComponent>(ecs, &p_info)?;
+        if prey.health <= 0.0 {
+            // Already dying
+            return Ok(ActionResult::OnGoing);
+        }
+        let damage_factor = if prey_sleeping {
+            config.creature.sleep_damage_factor
+        } else {
+            1.0
+        };
+        prey.health -= config.combat.attack_damage * size_ratio * damage_factor;
+        prey.last_attacker = Some(info.entity);
+        if prey.health <= 0.0 {
+            prey.killer = Some(info.entity);
+        }
+        if prey_sleeping {
+            return Ok(ActionResult::OnGoing);
+        }
+
+        // The prey fights back
+        let creature = get_comp_or_error::<AttackAction, CreatureComponent>(ecs, info)?;
+        creature.health -= config.combat.counter_damage / size_ratio;
+        creature.last_attacker = Some(prey_entity);
+        if creature.health <= 0.0 && creature.killer.is_none() {
+            creature.killer = Some(prey_entity);
+        }
+        Ok(ActionResult::OnGoing)
+    }
+
+    fn description(&self) -> String {
+        String::from("attack")
+    }
+}
+
+/// Eat the corpse of the prey killed
+pub struct EatPreyAction {
+    preconditions: [Condition; 1],
+    effects: [Effect; 3],
+    diet: DietConfig,
+}
+impl EatPreyAction {
+    pub fn new(config: &Config, diet: &DietConfig) -> Self {
+        let estimated_gain =
+            config.corpse_energy(config.max_creature_size(), 0.0) * diet.energy_yield;
         Self {
             preconditions: [Condition::new(
-                Symbol::IsNearHerbivorous,
+                Symbol::HasKilledPrey,
                 Operator::Equal,
                 Value::Bool(true),
             )],
@@ -190,16 +342,22 @@ impl EatHerbivorousAction {
                     Modifier::Increment,
                     Value::F32(OrderedFloat(estimated_gain)),
                 ),
+                Effect::clamp(
+                    Symbol::Energy,
+                    Value::F32(OrderedFloat(0.0)),
+                    Value::F32(OrderedFloat(config.creature.max_energy)),
+                ),
                 Effect::new(
-                    Symbol::IsNearHerbivorous,
+                    Symbol::HasKilledPrey,
                     Modifier::SetValue,
                     Value::Bool(false),
                 ),
             ],
+            diet: *diet,
         }
     }
 }
-impl Action for EatHerbivorousAction {
+impl Action for EatPreyAction {
     fn preconditions(&self) -> &[Condition] {
         &self.preconditions
     }
@@ -214,31 +372,487 @@ impl Action for EatHerbivorousAction {
         info: &EntityInfo,
         config: &Config,
     ) -> Result<ActionResult, String> {
-        // Get the target herbivorous entity ID
-        let agent = get_comp_or_error::<EatHerbivorousAction, AgentComponent>(ecs, info)?;
-        let herbivorous_entity = agent.target_entity;
+        // Get the corpse of the prey killed
+        let agent = get_comp_or_error::<EatPreyAction, AgentComponent>(ecs, info)?;
+        let corpse_entity = agent.target_entity;
         agent.target_entity = RESERVED_ENTITY_ID;
 
-        // Check if the target herbivorous still exists
-        if let Some(h_info) = ecs.get_entity_info(herbivorous_entity) {
-            // Increase energy
-            let creature = get_comp_or_error::<EatCorpseAction, CreatureComponent>(ecs, info)?;
-            creature.energy += config.creature.corpse_energy;
-            if creature.energy > config.creature.max_energy {
-                creature.energy = config.creature.max_energy;
-            }
+        // Check if the corpse still exists
+        if let Some(c_info) = ecs.get_entity_info(corpse_entity) {
+            eat_corpse::<EatPreyAction>(ecs, info, &c_info, &self.diet, config)?;
+            Ok(ActionResult::Success)
+        } else {
+            // Corpse not found, maybe it was stolen by a scavenger
+            Ok(ActionResult::Failure)
+        }
+    }
+
+    fn description(&self) -> String {
+        String::from("eat prey")
+    }
 
-            // Delete the herbivorous
-            ecs.apply(vec![Update::DeleteEntity(h_info)]);
+    fn cost_factor(&self) -> f64 {
+        1.0 / self.diet.preference
+    }
+}
 
+/// Sleep until the fatigue is fully recovered
+pub struct SleepAction {
+    effects: [Effect; 1],
+}
+impl SleepAction {
+    pub fn new() -> Self {
+        Self {
+            effects: [Effect::new(
+                Symbol::IsRested,
+                Modifier::SetValue,
+                Value::Bool(true),
+            )],
+        }
+    }
+}
+
+impl Action for SleepAction {
+    fn preconditions(&self) -> &[Condition] {
+        &[]
+    }
+
+    fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    fn perform(
+        &self,
+        ecs: &mut Ecs,
+        info: &EntityInfo,
+        config: &Config,
+    ) -> Result<ActionResult, String> {
+        let creature = get_comp_or_error::<SleepAction, CreatureComponent>(ecs, info)?;
+        creature.fatigue = f32::max(creature.fatigue - config.creature.sleep_recovery_rate, 0.0);
+        let is_rested = creature.fatigue <= 0.0;
+
+        let is_sleeping = ecs.has_component(info.arch_index, &to_ctype!(SleepingComponent));
+        if is_rested {
+            // Wake up
+            if is_sleeping {
+                ecs.apply(vec![Update::Delete {
+                    info: *info,
+                    c_type: to_ctype!(SleepingComponent),
+                }]);
+            }
             Ok(ActionResult::Success)
         } else {
-            // Herbivorous not found, maybe it was already eaten by someone else
-            Ok(ActionResult::Failure)
+            // Fall asleep
+            if !is_sleeping {
+                ecs.apply(vec![Update::Add {
+                    info: *info,
+                    comp: Box::new(SleepingComponent),
+                }]);
+            }
+            Ok(ActionResult::OnGoing)
         }
     }
 
     fn description(&self) -> String {
-        String::from("eat herbivorous")
+        String::from("sleep")
+    }
+}
+
+/// Drink at the target water source until the thirst is quenched
+pub struct DrinkAction {
+    preconditions: [Condition; 1],
+    effects: [Effect; 2],
+}
+impl DrinkAction {
+    pub fn new() -> Self {
+        Self {
+            preconditions: [Condition::new(
+                Symbol::IsNearWater,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+            effects: [
+                Effect::new(Symbol::IsHydrated, Modifier::SetValue, Value::Bool(true)),
+                Effect::new(Symbol::IsNearWater, Modifier::SetValue, Value::Bool(false)),
+            ],
+        }
+    }
+}
+
+impl Action for DrinkAction {
+    fn preconditions(&self) -> &[Condition] {
+        &self.preconditions
+    }
+
+    fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    fn perform(
+        &self,
+        ecs: &mut Ecs,
+        info: &EntityInfo,
+        config: &Config,
+    ) -> Result<ActionResult, String> {
+        // Check if the target water source still exists
+        let water_entity =
+            get_comp_or_error::<DrinkAction, AgentComponent>(ecs, info)?.target_entity;
+        if ecs
+            .component_from_entity::<WaterComponent>(water_entity)
+            .is_none()
+        {
+            return Ok(ActionResult::Failure);
+        }
+
+        let creature = get_comp_or_error::<DrinkAction, CreatureComponent>(ecs, info)?;
+        creature.thirst = f32::max(creature.thirst - config.creature.drink_rate, 0.0);
+        if creature.thirst > 0.0 {
+            return Ok(ActionResult::OnGoing);
+        }
+
+        let agent = get_comp_or_error::<DrinkAction, AgentComponent>(ecs, info)?;
+        agent.target_entity = RESERVED_ENTITY_ID;
+        Ok(ActionResult::Success)
+    }
+
+    fn description(&self) -> String {
+        String::from("drink")
+    }
+}
+
+/// Mate with the target creature, creating an offspring next to both parents
+pub struct MateAction {
+    // Goal the mate must be pursuing too (mates are of the same kind, with the same goal set)
+    reproduce_goal: usize,
+    preconditions: [Condition; 1],
+    effects: [Effect; 3],
+}
+impl MateAction {
+    pub fn new(config: &Config, reproduce_goal: usize) -> Self {
+        Self {
+            reproduce_goal,
+            preconditions: [Condition::new(
+                Symbol::IsNearMate,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+            effects: [
+                Effect::new(Symbol::HasMated, Modifier::SetValue, Value::Bool(true)),
+                Effect::new(Symbol::IsNearMate, Modifier::SetValue, Value::Bool(false)),
+                // Each parent pays half of the cost of the offspring
+                Effect::new(
+                    Symbol::Energy,
+                    Modifier::Decrement,
+                    Value::F32(OrderedFloat(config.creature.reprod_energy_cost / 2.0)),
+                ),
+            ],
+        }
+    }
+}
+
+impl Action for MateAction {
+    fn preconditions(&self) -> &[Condition] {
+        &self.preconditions
+    }
+
+    fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    fn perform(
+        &self,
+        ecs: &mut Ecs,
+        info: &EntityInfo,
+        config: &Config,
+    ) -> Result<ActionResult, String> {
+        // Get the target mate entity ID
+        let agent = get_comp_or_error::<MateAction, AgentComponent>(ecs, info)?;
+        let mate_entity = agent.target_entity;
+        agent.target_entity = RESERVED_ENTITY_ID;
+        let mut offspring_agent = agent.offspring();
+        if config.agent.inherit_action_stats {
+            offspring_agent.inherit_action_stats(agent);
+        }
+
+        // Check if the mate still exists, and is willing to mate: awake and pursuing the same goal
+        let Some(m_info) = ecs.get_entity_info(mate_entity) else {
+            return Ok(ActionResult::Failure);
+        };
+        let is_willing = !ecs.has_component(m_info.arch_index, &to_ctype!(SleepingComponent))
+            && ecs
+                .component::<AgentComponent>(&m_info)
+                .is_some_and(|mate_agent| mate_agent.pursues_goal(self.reproduce_goal));
+        if !is_willing {
+            return Ok(ActionResult::Failure);
+        }
+
+        // The cost of the offspring is shared by the parents, who both need enough energy
+        let parent_cost = config.creature.reprod_energy_cost / 2.0;
+        let can_mate = |ecs: &Ecs, info: &EntityInfo| {
+            utils::can_reproduce(ecs, info)
+                && ecs
+                    .component::<CreatureComponent>(info)
+                    .is_some_and(|c| c.energy >= parent_cost)
+        };
+        if !can_mate(ecs, info) || !can_mate(ecs, &m_info) {
+            return Ok(ActionResult::Failure);
+        }
+
+        // The mate must be in contact
+        let body = *get_comp_or_error::<MateAction, BodyComponent>(ecs, info)?;
+        let mate_body = *get_comp_or_error::<MateAction, BodyComponent>(ecs, &m_info)?;
+        if !body.almost_collides(&mate_body, config.collision.contact_center_2_center_factor) {
+            return Ok(ActionResult::Failure);
+        }
+
+        // The offspring inherits the traits of both parents
+        let species = *get_comp_or_error::<MateAction, SpeciesComponent>(ecs, info)?;
+        let base_genome = GenomeComponent::new(&config.creature, species.config(config));
+        let genome_of = |ecs: &Ecs, info: &EntityInfo| {
+            ecs.component::<GenomeComponent>(info)
+                .copied()
+                .unwrap_or(base_genome)
+        };
+        let genome = genome_of(ecs, info).offspring(
+            &genome_of(ecs, &m_info),
+            &base_genome,
+            &config.genome,
+            (
+                config.creature.reprod_energy_cost,
+                config.creature.max_energy,
+            ),
+        );
+
+        // Place the offspring in a free space around the parents
+        let offspring_size = genome.size * config.aging.newborn_size_factor;
+        let Some(offspring_body) = utils::find_offspring_position(
+            &body,
+            &mate_body,
+            offspring_size,
+            (body.w() + offspring_size) / 2.0 + config.creature.reprod_x_offset,
+        ) else {
+            return Ok(ActionResult::Failure);
+        };
+
+        // Apply reproduction energy cost to both parents
+        for parent_info in [info, &m_info] {
+            let creature = get_comp_or_error::<MateAction, CreatureComponent>(ecs, parent_info)?;
+            creature.energy -= parent_cost;
+        }
+
+        // Create the offspring
+        ecs.apply(vec![Update::Create(utils::creature_components(
+            config,
+            species,
+            genome,
+            offspring_body,
+            AgeComponent::new(species.config(config).lifespan, &config.aging),
+            offspring_agent,
+        ))]);
+
+        Ok(ActionResult::Success)
+    }
+
+    fn description(&self) -> String {
+        String::from("mate")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        actions::all::{AttackAction, MateAction},
+        algorithms::rng,
+        components::{
+            agent_component::AgentComponent,
+            all::{CorpseComponent, CreatureComponent, SleepingComponent, SpeciesComponent},
+            body_component::BodyComponent,
+        },
+        configuration::{Config, test_config},
+        ecs::{
+            Component, Ecs, EntityId, EntityInfo, System, Update, iter_components, iter_entities,
+        },
+        goap::{Action, ActionResult},
+        shared_data::{biome, body_grid},
+        systems::death_system::DeathSystem,
+    };
+    use std::any::TypeId;
+
+    #[test]
+    fn test_mate() {
+        let config = test_config();
+        rng::init(&config);
+        body_grid::init(&config);
+        biome::init_without_water(&config);
+        let reproduce_goal = 1;
+        let action = MateAction::new(&config, reproduce_goal);
+
+        // A parent, a mate in contact with it and another one too far
+        let size = config.species[0].size;
+        let mut ecs = Ecs::new();
+        for y in [0.0, size / 2.0, size * 10.0] {
+            let mut agent = AgentComponent::new(0, 0, 2);
+            agent.goal = Some(reproduce_goal);
+            ecs.apply(vec![Update::Create(vec![
+                Box::new(CreatureComponent {
+                    energy: config.creature.reprod_energy_cost,
+                    ..CreatureComponent::new(&config.creature)
+                }),
+                Box::new(BodyComponent::new_not_traversable(0.0, y, size, size)),
+                Box::new(SpeciesComponent::new(0)),
+                Box::new(agent),
+            ])]);
+        }
+        let entities: Vec<EntityId> = iter_components!(ecs, (), (AgentComponent))
+            .map(|(_, info)| info.entity)
+            .collect();
+        let (parent, mate, far_mate) = (entities[0], entities[1], entities[2]);
+
+        let mate_with = |ecs: &mut Ecs, mate: EntityId| {
+            let info = ecs.get_entity_info(parent).unwrap();
+            ecs.component_mut::<AgentComponent>(&info)
+                .unwrap()
+                .target_entity = mate;
+            action.perform(ecs, &info, &config).unwrap()
+        };
+        let set_mate_goal = |ecs: &mut Ecs, goal: Option<usize>| {
+            let info = ecs.get_entity_info(mate).unwrap();
+            ecs.component_mut::<AgentComponent>(&info).unwrap().goal = goal;
+        };
+
+        assert!(matches!(
+            mate_with(&mut ecs, far_mate),
+            ActionResult::Failure
+        ));
+
+        // The mate must be pursuing its reproduce goal, and be awake
+        set_mate_goal(&mut ecs, None);
+        assert!(matches!(mate_with(&mut ecs, mate), ActionResult::Failure));
+        set_mate_goal(&mut ecs, Some(reproduce_goal));
+        let info = ecs.get_entity_info(mate).unwrap();
+        ecs.apply(vec![Update::Add {
+            info,
+            comp: Box::new(SleepingComponent),
+        }]);
+        assert!(matches!(mate_with(&mut ecs, mate), ActionResult::Failure));
+        let info = ecs.get_entity_info(mate).unwrap();
+        let mut updates = Vec::new();
+        Ecs::push_delete::<SleepingComponent>(info, &mut updates);
+        ecs.apply(updates);
+
+        assert!(matches!(mate_with(&mut ecs, mate), ActionResult::Success));
+        assert_eq!(iter_components!(ecs, (), (AgentComponent)).count(), 4);
+        for entity in [parent, mate] {
+            let info = ecs.get_entity_info(entity).unwrap();
+            let creature = ecs.component::<CreatureComponent>(&info).unwrap();
+            assert_eq!(creature.energy, config.creature.reprod_energy_cost / 2.0);
+        }
+    }
+
+    /// A predator targeting a prey of the same size in contact with it (the prey may be asleep)
+    fn predator_and_prey(config: &Config, prey_sleeping: bool) -> (Ecs, EntityInfo, EntityId) {
+        let size = config.species[0].size;
+        let mut prey: Vec<Box<dyn Component>> = vec![
+            Box::new(BodyComponent::new_not_traversable(size, 0.0, size, size)),
+            Box::new(CreatureComponent::new(&config.creature)),
+        ];
+        if prey_sleeping {
+            prey.push(Box::new(SleepingComponent));
+        }
+        let mut ecs = Ecs::new();
+        ecs.apply(vec![
+            Update::Create(vec![
+                Box::new(BodyComponent::new_not_traversable(0.0, 0.0, size, size)),
+                Box::new(CreatureComponent::new(&config.creature)),
+                Box::new(AgentComponent::new(0, 0, 0)),
+            ]),
+            Update::Create(prey),
+        ]);
+        let info = iter_entities!(ecs, AgentComponent).next().unwrap();
+        let prey = iter_entities!(ecs, CreatureComponent)
+            .find(|i| i.entity != info.entity)
+            .unwrap()
+            .entity;
+        ecs.component_mut::<AgentComponent>(&info)
+            .unwrap()
+            .target_entity = prey;
+        (ecs, info, prey)
+    }
+
+    fn health(ecs: &Ecs, entity: EntityId) -> f32 {
+        let info = ecs.get_entity_info(entity).unwrap();
+        ecs.component::<CreatureComponent>(&info).unwrap().health
+    }
+
+    #[test]
+    fn test_attack() {
+        let config = test_config();
+        body_grid::init(&config);
+        let (max_health, combat) = (config.creature.max_health, config.combat);
+        let (mut ecs, info, prey) = predator_and_prey(&config, false);
+        let attack = AttackAction::new();
+
+        // Each attack wounds the prey, which fights back
+        let lethal_attacks = (max_health / combat.attack_damage).ceil() as usize;
+        assert!(lethal_attacks > 1);
+        for _ in 1..lethal_attacks {
+            let result = attack.perform(&mut ecs, &info, &config).unwrap();
+            assert!(matches!(result, ActionResult::OnGoing));
+        }
+        let wounds = (lethal_attacks - 1) as f32;
+        assert_eq!(
+            health(&ecs, prey),
+            max_health - wounds * combat.attack_damage
+        );
+        assert_eq!(
+            health(&ecs, info.entity),
+            max_health - wounds * combat.counter_damage
+        );
+        let prey_info = ecs.get_entity_info(prey).unwrap();
+        let prey_creature = ecs.component::<CreatureComponent>(&prey_info).unwrap();
+        assert_eq!(prey_creature.last_attacker, Some(info.entity));
+        assert_eq!(prey_creature.killer, None);
+
+        // The last attack kills the prey
+        attack.perform(&mut ecs, &info, &config).unwrap();
+        let prey_info = ecs.get_entity_info(prey).unwrap();
+        let prey_creature = ecs.component::<CreatureComponent>(&prey_info).unwrap();
+        assert_eq!(prey_creature.killer, Some(info.entity));
+
+        // Its corpse is handed off to the predator
+        DeathSystem.run(&mut ecs, &config);
+        assert!(ecs.get_entity_info(prey).is_none());
+        let corpse = iter_entities!(ecs, CorpseComponent).next().unwrap();
+        assert_eq!(
+            ecs.component::<CorpseComponent>(&corpse).unwrap().killer,
+            Some(info.entity)
+        );
+        let info = ecs.get_entity_info(info.entity).unwrap();
+        let result = attack.perform(&mut ecs, &info, &config).unwrap();
+        assert!(matches!(result, ActionResult::Success));
+        assert_eq!(
+            ecs.component::<AgentComponent>(&info)
+                .unwrap()
+                .target_entity,
+            corpse.entity
+        );
+    }
+
+    #[test]
+    fn test_attack_sleeping_prey() {
+        let config = test_config();
+        body_grid::init(&config);
+        let (max_health, combat) = (config.creature.max_health, config.combat);
+        let damage = combat.attack_damage * config.creature.sleep_damage_factor;
+        assert!(damage < max_health);
+        let (mut ecs, info, prey) = predator_and_prey(&config, true);
+
+        // More damage is dealt to a sleeping prey, which does not fight back
+        let result = AttackAction::new()
+            .perform(&mut ecs, &info, &config)
+            .unwrap();
+        assert!(matches!(result, ActionResult::OnGoing));
+        assert_eq!(health(&ecs, prey), max_health - damage);
+        assert_eq!(health(&ecs, info.entity), max_health);
     }
 }
@@ -2,29 +2,27 @@ use crate::{
     actions::all::get_comp_or_error,
     components::{
         agent_component::AgentComponent,
-        all::{CorpseComponent, HerbivorousComponent, MoveToTargetResultComponent, PlantComponent},
+        all::{
+            CorpseComponent, CreatureComponent, MoveToTargetResultComponent, PerceptionComponent,
+            PlantComponent, SpeciesComponent, WaterComponent,
+        },
         body_component::BodyComponent,
+        genome_component::GenomeComponent,
+        inbox_component::{FoodKind, Signal},
+        memory_component::MemoryKind,
         move_to_target_component::MoveToTargetComponent,
     },
     configuration::Config,
-    ecs::{Component, Ecs, EntityInfo, Update, to_ctype},
+    ecs::{Component, Ecs, EntityInfo, RESERVED_ENTITY_ID, Update, to_ctype},
     goap::{Action, ActionResult, Condition, Effect, Modifier, Symbol, Value},
-    systems::utils,
+    systems::{message_system, utils},
 };
 use std::any::TypeId;
+use std::f64::consts::PI;
 
-/// Create a MoveToTarget component and let the corresponding system handle the move.
-/// Consider that the move is finished when the entity has a [`MoveToTargetResultComponent`] component.
-fn perform_move_to_target_action<A, T>(
-    ecs: &mut Ecs,
-    info: &EntityInfo,
-    config: &Config,
-    speed: f64,
-) -> Result<ActionResult, String>
-where
-    A: Action,
-    T: Component,
-{
+/// Return the result of the move if it is over, or OnGoing if it is on-going.
+/// Return None if there is no move initiated.
+fn move_to_target_result(ecs: &mut Ecs, info: &EntityInfo) -> Option<ActionResult> {
     // If the move is over, return the result
     if let Some(result) = ecs.component::<MoveToTargetResultComponent>(info).cloned() {
         ecs.apply(vec![Update::Delete {
@@ -32,15 +30,64 @@ where
             c_type: to_ctype!(MoveToTargetResultComponent),
         }]);
         return if result.success {
-            Ok(ActionResult::Success)
+            Some(ActionResult::Success)
         } else {
-            Ok(ActionResult::Failure)
+            Some(ActionResult::Failure)
         };
     }
 
     // If the move is on-going, do nothing
     if ecs.has_component(info.arch_index, &to_ctype!(MoveToTargetComponent)) {
-        return Ok(ActionResult::OnGoing);
+        return Some(ActionResult::OnGoing);
+    }
+    None
+}
+
+/// Create a MoveToTarget component and let the corresponding system handle the move.
+/// Consider that the move is finished when the entity has a [`MoveToTargetResultComponent`] component.
+/// If no target is within range, move to the closest one remembered (of the given kind).
+fn perform_move_to_target_action<A, T>(
+    ecs: &mut Ecs,
+    info: &EntityInfo,
+    config: &Config,
+    speed: f64,
+    memory_kind: MemoryKind,
+) -> Result<ActionResult, String>
+where
+    A: Action,
+    T: Component,
+{
+    perform_move_to_filtered_target_action::<A, T, _>(
+        ecs,
+        info,
+        config,
+        speed,
+        Some(memory_kind),
+        false,
+        |_, _| true,
+    )
+}
+
+/// Same as [`perform_move_to_target_action`], ignoring the targets rejected by the filter
+/// (the memory is only used with a kind given).
+/// The food seen is reported to the creatures of the same species nearby, and a mating call is
+/// sent when no mate is found. Moving targets can be chased (pursuit mode).
+fn perform_move_to_filtered_target_action<A, T, F>(
+    ecs: &mut Ecs,
+    info: &EntityInfo,
+    config: &Config,
+    speed: f64,
+    memory_kind: Option<MemoryKind>,
+    pursuit: bool,
+    is_valid_target: F,
+) -> Result<ActionResult, String>
+where
+    A: Action,
+    T: Component,
+    F: Fn(&Ecs, &EntityInfo) -> bool,
+{
+    if let Some(result) = move_to_target_result(ecs, info) {
+        return Ok(result);
     }
 
     // If we reach this point, the move must be initiated
@@ -48,26 +95,70 @@ where
     // Get the body component
     let body = *get_comp_or_error::<A, BodyComponent>(ecs, info)?;
 
-    // Find the closest reachable entity (if there is one)
-    if let Some((_, closest_entity, closest_body, closest_path)) =
-        utils::find_closest_reachable::<T>(ecs, config, info.entity, &body)
+    // Find the closest reachable entity (if there is one), or else the closest remembered one
+    let seen = utils::find_closest_reachable::<T, _>(ecs, config, info, &body, is_valid_target)
+        .map(|(_, entity, body, path)| (entity, body, path));
+    if let Some((entity, target_body, _)) = &seen
+        && let Some(kind) = memory_kind.and_then(FoodKind::from_memory)
     {
+        message_system::broadcast(
+            ecs,
+            info,
+            &body,
+            Signal::FoodFound(kind),
+            *entity,
+            (target_body.x(), target_body.y()),
+            config,
+        );
+    }
+    if seen.is_none() && memory_kind == Some(MemoryKind::Mate) {
+        message_system::broadcast(
+            ecs,
+            info,
+            &body,
+            Signal::MatingCall,
+            info.entity,
+            (body.x(), body.y()),
+            config,
+        );
+    }
+    // A remembered entity is looked for at its remembered position: the move does not follow it
+    let closest = seen
+        .map(|(entity, body, path)| (entity, entity, body, path))
+        .or_else(|| {
+            memory_kind
+                .and_then(|kind| {
+                    utils::find_closest_remembered::<T>(ecs, config, info, &body, kind)
+                })
+                .map(|(entity, body, path)| (entity, RESERVED_ENTITY_ID, body, path))
+        });
+    if let Some((closest_entity, move_target_entity, closest_body, closest_path)) = closest {
         let agent = get_comp_or_error::<A, AgentComponent>(ecs, info)?;
         agent.target_entity = closest_entity;
 
         // Target found, initiate the move
+        let move_to_target =
+            MoveToTargetComponent::new(move_target_entity, closest_body, closest_path, speed);
         ecs.apply(vec![Update::Add {
             info: *info,
-            comp: Box::new(MoveToTargetComponent::new(
-                closest_entity,
-                closest_body,
-                closest_path,
-                speed,
-            )),
+            // Only a target seen is pursued (a remembered one is looked for at its position)
+            comp: if pursuit && move_target_entity != RESERVED_ENTITY_ID {
+                Box::new(move_to_target.with_pursuit())
+            } else {
+                Box::new(move_to_target)
+            },
         }]);
         Ok(ActionResult::OnGoing)
     } else {
-        // No target found, go into idle state to lower cpu load
+        // No target found, look in another direction for the next search
+        let field_of_view = ecs
+            .component::<GenomeComponent>(info)
+            .map_or(2.0 * PI, |genome| genome.field_of_view);
+        if let Some(perception) = ecs.component_mut::<PerceptionComponent>(info) {
+            perception.look_around(field_of_view);
+        }
+
+        // Go into idle state to lower cpu load
         let agent = get_comp_or_error::<A, AgentComponent>(ecs, info)?;
         agent.go_idle();
         Ok(ActionResult::Failure)
@@ -103,11 +194,13 @@ impl Action for MoveToNearestPlantAction {
         info: &EntityInfo,
         config: &Config,
     ) -> Result<ActionResult, String> {
+        let speed = utils::creature_speed(ecs, info, config);
         perform_move_to_target_action::<MoveToNearestPlantAction, PlantComponent>(
             ecs,
             info,
             config,
-            config.creature.herbivorous_speed,
+            speed,
+            MemoryKind::Plant,
         )
     }
 
@@ -145,11 +238,13 @@ impl Action for MoveToNearestCorpseAction {
         info: &EntityInfo,
         config: &Config,
     ) -> Result<ActionResult, String> {
+        let speed = utils::creature_speed(ecs, info, config);
         perform_move_to_target_action::<MoveToNearestCorpseAction, CorpseComponent>(
             ecs,
             info,
             config,
-            config.creature.carnivorous_speed,
+            speed,
+            MemoryKind::Corpse,
         )
     }
 
@@ -158,21 +253,118 @@ impl Action for MoveToNearestCorpseAction {
     }
 }
 
-pub struct MoveToNearestHerbivorousAction {
+pub struct MoveToNearestWaterAction {
+    effects: [Effect; 1],
+}
+impl MoveToNearestWaterAction {
+    pub fn new() -> Self {
+        Self {
+            effects: [Effect::new(
+                Symbol::IsNearWater,
+                Modifier::SetValue,
+                Value::Bool(true),
+            )],
+        }
+    }
+}
+impl Action for MoveToNearestWaterAction {
+    fn preconditions(&self) -> &[Condition] {
+        &[]
+    }
+
+    fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    fn perform(
+        &self,
+        ecs: &mut Ecs,
+        info: &EntityInfo,
+        config: &Config,
+    ) -> Result<ActionResult, String> {
+        let speed = utils::creature_speed(ecs, info, config);
+        perform_move_to_target_action::<MoveToNearestWaterAction, WaterComponent>(
+            ecs,
+            info,
+            config,
+            speed,
+            MemoryKind::Water,
+        )
+    }
+
+    fn description(&self) -> String {
+        String::from("move to nearest water")
+    }
+}
+
+/// Move to the closest creature of a species hunted by the agent's species, or else to the
+/// closest one remembered
+pub struct MoveToNearestPreyAction {
+    effects: [Effect; 1],
+}
+impl MoveToNearestPreyAction {
+    pub fn new() -> Self {
+        Self {
+            effects: [Effect::new(
+                Symbol::IsNearPrey,
+                Modifier::SetValue,
+                Value::Bool(true),
+            )],
+        }
+    }
+}
+impl Action for MoveToNearestPreyAction {
+    fn preconditions(&self) -> &[Condition] {
+        &[]
+    }
+
+    fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    fn perform(
+        &self,
+        ecs: &mut Ecs,
+        info: &EntityInfo,
+        config: &Config,
+    ) -> Result<ActionResult, String> {
+        let species = *get_comp_or_error::<MoveToNearestPreyAction, SpeciesComponent>(ecs, info)?;
+        let speed = utils::creature_speed(ecs, info, config);
+        perform_move_to_filtered_target_action::<MoveToNearestPreyAction, SpeciesComponent, _>(
+            ecs,
+            info,
+            config,
+            speed,
+            Some(MemoryKind::Prey),
+            true,
+            |ecs, target_info| {
+                ecs.component::<SpeciesComponent>(target_info)
+                    .is_some_and(|target| config.is_prey_of(target.species, species.species))
+            },
+        )
+    }
+
+    fn description(&self) -> String {
+        String::from("move to nearest prey")
+    }
+}
+
+/// Move to the closest creature of the same species ready to mate
+pub struct MoveToMateAction {
     effects: [Effect; 1],
 }
-impl MoveToNearestHerbivorousAction {
+impl MoveToMateAction {
     pub fn new() -> Self {
         Self {
             effects: [Effect::new(
-                Symbol::IsNearHerbivorous,
+                Symbol::IsNearMate,
                 Modifier::SetValue,
                 Value::Bool(true),
             )],
         }
     }
 }
-impl Action for MoveToNearestHerbivorousAction {
+impl Action for MoveToMateAction {
     fn preconditions(&self) -> &[Condition] {
         &[]
     }
@@ -187,17 +379,110 @@ impl Action for MoveToNearestHerbivorousAction {
         info: &EntityInfo,
         config: &Config,
     ) -> Result<ActionResult, String> {
-        perform_move_to_target_action::<MoveToNearestHerbivorousAction, HerbivorousComponent>(
+        let species = *get_comp_or_error::<MoveToMateAction, SpeciesComponent>(ecs, info)?;
+        let speed = utils::creature_speed(ecs, info, config);
+        perform_move_to_filtered_target_action::<MoveToMateAction, SpeciesComponent, _>(
             ecs,
             info,
             config,
-            // TODO speed should depend on the running agent ideally (so that the action may be
-            // attributed to herbivorous agents also, not just carninvorous)
-            config.creature.carnivorous_speed,
+            speed,
+            Some(MemoryKind::Mate),
+            false,
+            |ecs, target_info| {
+                if ecs.component::<SpeciesComponent>(target_info) != Some(&species)
+                    || !utils::can_reproduce(ecs, target_info)
+                {
+                    return false;
+                }
+                let threshold = ecs
+                    .component::<GenomeComponent>(target_info)
+                    .map_or(config.creature.reprod_energy_threshold, |genome| {
+                        genome.reprod_energy_threshold
+                    });
+                ecs.component::<CreatureComponent>(target_info)
+                    .is_some_and(|c| c.energy >= threshold)
+            },
         )
     }
 
     fn description(&self) -> String {
-        String::from("move to nearest herbivorous")
+        String::from("move to mate")
+    }
+}
+
+/// Move to a position away from the predators nearby
+pub struct FleeAction {
+    effects: [Effect; 1],
+}
+impl FleeAction {
+    pub fn new() -> Self {
+        Self {
+            effects: [Effect::new(
+                Symbol::IsSafe,
+                Modifier::SetValue,
+                Value::Bool(true),
+            )],
+        }
+    }
+}
+impl Action for FleeAction {
+    fn preconditions(&self) -> &[Condition] {
+        &[]
+    }
+
+    fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    fn perform(
+        &self,
+        ecs: &mut Ecs,
+        info: &EntityInfo,
+        config: &Config,
+    ) -> Result<ActionResult, String> {
+        if let Some(result) = move_to_target_result(ecs, info) {
+            return Ok(result);
+        }
+
+        let body = *get_comp_or_error::<FleeAction, BodyComponent>(ecs, info)?;
+        let predators = utils::predators_of(ecs, info, config);
+
+        let threats: Vec<BodyComponent> =
+            utils::known_threats(ecs, info, &body, &predators, &config.creature)
+                .into_iter()
+                .map(|(_, threat_body)| threat_body)
+                .collect();
+
+        // Already safe
+        if threats.is_empty() {
+            return Ok(ActionResult::Success);
+        }
+
+        if let Some((position, path)) = utils::find_flee_position(
+            config,
+            info.entity,
+            &body,
+            &threats,
+            config.creature.flee_distance,
+        ) {
+            // The target is a position, not an entity
+            ecs.apply(vec![Update::Add {
+                info: *info,
+                comp: Box::new(MoveToTargetComponent::new(
+                    RESERVED_ENTITY_ID,
+                    position,
+                    path,
+                    utils::creature_speed(ecs, info, config),
+                )),
+            }]);
+            Ok(ActionResult::OnGoing)
+        } else {
+            // Cornered
+            Ok(ActionResult::Failure)
+        }
+    }
+
+    fn description(&self) -> String {
+        String::from("flee")
     }
 }
@@ -2,7 +2,7 @@ use crate::algorithms::rng;
 use crate::components::body_component::BodyComponent;
 use crate::configuration::Config;
 use crate::ecs::EntityId;
-use crate::shared_data::body_grid;
+use crate::shared_data::{biome, body_grid};
 use ordered_float::OrderedFloat;
 use std::collections::HashMap;
 
@@ -173,14 +173,16 @@ impl Graph {
             for cell_y in min_y..(max_y + 1) {
                 let cell_center_y = coords.to_y(cell_y);
 
-                // Add the node to graph only if the corresponding cell is not colliding anything
+                // Add the node to graph only if the corresponding cell is on land and not colliding anything
                 let cell_body = BodyComponent::new_traversable(
                     cell_center_x,
                     cell_center_y,
                     grid_cell_size,
                     grid_cell_size,
                 );
-                if body_grid::collides_except_target(entity, target_entity, &cell_body) {
+                if biome::is_water(cell_center_x, cell_center_y)
+                    || body_grid::collides_except_target(entity, target_entity, &cell_body)
+                {
                     continue;
                 }
 
@@ -266,6 +268,9 @@ impl Graph {
         for _ in 0..config.path.nb_prm_positions_generated {
             let x = rng::random_range(center_x - r, center_x + r);
             let y = rng::random_range(center_y - r, center_y + r);
+            if biome::is_water(x, y) {
+                continue;
+            }
             let node = Node::new(x, y);
             self.neighbours.entry(node).or_default();
         }
@@ -345,7 +350,9 @@ fn add_neighbour_cell_center(
         grid_cell_size,
         grid_cell_size,
     );
-    if !body_grid::collides_except_target(entity, target_entity, &cell_body) {
+    if !biome::is_water(cell_center_x, cell_center_y)
+        && !body_grid::collides_except_target(entity, target_entity, &cell_body)
+    {
         neighbours.push(Node::new_cell_center(cell_center_x, cell_center_y));
     }
 }
@@ -439,7 +446,12 @@ pub fn find_reverse_path(graph: &Graph, start: Node, goal: Node) -> Option<Vec<N
         for v in graph.neighbours_of(&u).iter() {
             // Check if this path is better than any previous one that passes through v.
             // To do this, compute the length of the path from start to v.
-            let try_g_cost = *g_cost.get(&u).unwrap() + square_euclidian_distance(&u, v);
+            // The terrain makes the edge longer, and water cannot be crossed
+            let Some(edge_cost) = biome::edge_cost((u.x(), u.y()), (v.x(), v.y())) else {
+                continue;
+            };
+            let try_g_cost =
+                *g_cost.get(&u).unwrap() + square_euclidian_distance(&u, v) * edge_cost;
             let g_cost_v = g_cost.get(v);
             if g_cost_v.is_none() || try_g_cost < *g_cost_v.unwrap() {
                 // Best path through v ! Estimate total distance to the goal
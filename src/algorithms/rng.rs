@@ -20,6 +20,20 @@ pub fn init(config: &Config) {
     });
 }
 
+/// Seed the generator without a configuration (for the tests)
+#[cfg(test)]
+pub fn init_with_seed(seed: u64) {
+    RNG.with_borrow_mut(|rng| *rng = Some(SmallRng::seed_from_u64(seed)));
+}
+
 pub fn random_range(lower_bound: f64, upper_bound: f64) -> f64 {
     RNG.with_borrow_mut(|rng| rng.as_mut().unwrap().random_range(lower_bound..upper_bound))
 }
+
+/// Sample a normal distribution (Box-Muller transform)
+pub fn random_normal(mean: f64, std_dev: f64) -> f64 {
+    // u1 in ]0; 1] so that its logarithm is defined
+    let u1 = 1.0 - random_range(0.0, 1.0);
+    let u2 = random_range(0.0, 1.0);
+    mean + std_dev * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
@@ -0,0 +1,143 @@
+use crate::algorithms::rng;
+use crate::configuration::AgingConfig;
+use crate::ecs::Component;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LifeStage {
+    // Growing, cannot reproduce
+    Juvenile,
+    Adult,
+    // Slower, cannot reproduce
+    Elder,
+}
+
+#[derive(Clone, Copy)]
+pub struct AgeComponent {
+    pub age: usize,
+    // Age at which the creature dies naturally
+    pub lifespan: usize,
+    pub stage: LifeStage,
+}
+impl Component for AgeComponent {}
+impl AgeComponent {
+    /// Newborn, with a lifespan varying around the lifespan of its species
+    pub fn new(lifespan: usize, config: &AgingConfig) -> Self {
+        let lifespan = lifespan as f64 * (1.0 + rng::random_normal(0.0, config.lifespan_std_dev));
+        let mut age = Self {
+            age: 0,
+            lifespan: lifespan.max(1.0) as usize,
+            stage: LifeStage::Juvenile,
+        };
+        age.update_stage(config);
+        age
+    }
+
+    /// Adult of a random age
+    pub fn new_adult(lifespan: usize, config: &AgingConfig) -> Self {
+        let mut age = Self::new(lifespan, config);
+        age.age = (age.lifespan as f64 * rng::random_range(config.juvenile_end, config.elder_start))
+            as usize;
+        age.update_stage(config);
+        age
+    }
+
+    /// Get one tick older. Return false when the lifespan is over.
+    pub fn grow_older(&mut self, config: &AgingConfig) -> bool {
+        self.age += 1;
+        self.update_stage(config);
+        self.age < self.lifespan
+    }
+
+    fn update_stage(&mut self, config: &AgingConfig) {
+        let progress = self.age as f64 / self.lifespan as f64;
+        self.stage = if progress < config.juvenile_end {
+            LifeStage::Juvenile
+        } else if progress < config.elder_start {
+            LifeStage::Adult
+        } else {
+            LifeStage::Elder
+        };
+    }
+
+    pub fn can_reproduce(&self) -> bool {
+        self.stage == LifeStage::Adult
+    }
+
+    /// Size relative to the adult size: juveniles grow from the newborn size
+    pub fn size_factor(&self, config: &AgingConfig) -> f64 {
+        if self.stage != LifeStage::Juvenile {
+            return 1.0;
+        }
+        let growth = self.age as f64 / (self.lifespan as f64 * config.juvenile_end);
+        config.newborn_size_factor + (1.0 - config.newborn_size_factor) * growth.min(1.0)
+    }
+
+    pub fn speed_factor(&self, config: &AgingConfig) -> f64 {
+        match self.stage {
+            LifeStage::Juvenile => config.juvenile_speed_factor,
+            LifeStage::Adult => 1.0,
+            LifeStage::Elder => config.elder_speed_factor,
+        }
+    }
+
+    pub fn description(&self) -> Vec<String> {
+        vec![
+            "AGE".to_string(),
+            format!(
+                "    {:?}, {} / {} ticks",
+                self.stage, self.age, self.lifespan
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        components::age_component::{AgeComponent, LifeStage},
+        configuration::AgingConfig,
+    };
+
+    #[test]
+    fn test_life_stages() {
+        let config = AgingConfig {
+            juvenile_end: 0.2,
+            elder_start: 0.8,
+            lifespan_std_dev: 0.0,
+            newborn_size_factor: 0.5,
+            juvenile_speed_factor: 0.8,
+            elder_speed_factor: 0.5,
+        };
+        let mut age = AgeComponent {
+            age: 0,
+            lifespan: 100,
+            stage: LifeStage::Juvenile,
+        };
+        assert_eq!(age.size_factor(&config), 0.5);
+        assert!(!age.can_reproduce());
+
+        for _ in 0..10 {
+            assert!(age.grow_older(&config));
+        }
+        assert_eq!(age.size_factor(&config), 0.75);
+
+        for _ in 0..10 {
+            assert!(age.grow_older(&config));
+        }
+        assert_eq!(age.stage, LifeStage::Adult);
+        assert_eq!(age.size_factor(&config), 1.0);
+        assert!(age.can_reproduce());
+
+        for _ in 0..60 {
+            assert!(age.grow_older(&config));
+        }
+        assert_eq!(age.stage, LifeStage::Elder);
+        assert_eq!(age.speed_factor(&config), 0.5);
+        assert!(!age.can_reproduce());
+
+        for _ in 0..19 {
+            assert!(age.grow_older(&config));
+        }
+        assert!(!age.grow_older(&config));
+    }
+}
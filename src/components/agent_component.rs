@@ -1,18 +1,83 @@
 use crate::{
-    configuration::Config,
+    configuration::{AgentConfig, Config},
     ecs::{Component, EntityId, RESERVED_ENTITY_ID},
-    goap::{Goap, WorldState},
+    goap::{Goap, PlannerState, WorldState},
+    htn::Agenda,
 };
 use log::error;
 
+/// Measured outcomes of an action performed by an agent, as decaying averages (recent outcomes
+/// weigh more), and the resulting planning cost
+#[derive(Clone)]
+pub struct ActionStats {
+    ticks: f64,
+    energy: f64,
+    success_rate: f64,
+    nb_samples: usize,
+    cost: f64,
+}
+
+impl ActionStats {
+    fn new() -> Self {
+        Self {
+            ticks: 0.0,
+            energy: 0.0,
+            success_rate: 1.0,
+            nb_samples: 0,
+            cost: 1.0,
+        }
+    }
+
+    fn record(&mut self, ticks: f64, energy: f64, success: bool, config: &AgentConfig) {
+        // The first sample is taken as is
+        let rate = if self.nb_samples == 0 {
+            1.0
+        } else {
+            config.action_cost_learning_rate
+        };
+        self.ticks += rate * (ticks - self.ticks);
+        self.energy += rate * (energy - self.energy);
+        self.success_rate += rate * (if success { 1.0 } else { 0.0 } - self.success_rate);
+        self.nb_samples += 1;
+
+        // Base cost of 1, increased by the resources spent, and divided by the chances of success
+        self.cost = (1.0
+            + config.action_cost_ticks_factor * self.ticks
+            + config.action_cost_energy_factor * self.energy)
+            / self.success_rate.max(config.action_min_success_rate);
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+
+    pub fn description(&self) -> String {
+        format!(
+            "cost {:.2} ({} samples, {:.0}% success, {:.0} ticks, {:.1} energy)",
+            self.cost,
+            self.nb_samples,
+            self.success_rate * 100.0,
+            self.ticks,
+            self.energy
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct AgentComponent {
     pub goal: Option<usize>,
     goal_set: usize,
     action_set: usize,
-    action_costs: Vec<f64>,
+    action_stats: Vec<ActionStats>,
+    // Measures of the current action, since it started
+    action_ticks: usize,
+    action_start_energy: f32,
     plan: Vec<usize>,
     current_action_index_in_plan: usize,
+    // On-going plan search, resumed on the next ticks if the planning budget is exhausted
+    planner: Option<PlannerState>,
+    // Remaining tasks of the compound task being pursued, if any
+    agenda: Option<Agenda>,
     pub world_state: WorldState,
     idle: bool,
     idle_ticks_count: usize,
@@ -27,9 +92,13 @@ impl AgentComponent {
             goal: None,
             goal_set,
             action_set,
-            action_costs: vec![1.0; action_set_len],
+            action_stats: vec![ActionStats::new(); action_set_len],
+            action_ticks: 0,
+            action_start_energy: 0.0,
             plan: Vec::new(),
             current_action_index_in_plan: 0,
+            planner: None,
+            agenda: None,
             world_state: WorldState::new(),
             idle: false,
             idle_ticks_count: 0,
@@ -38,6 +107,7 @@ impl AgentComponent {
     }
 
     pub fn next_action(&mut self) {
+        self.action_ticks = 0;
         self.current_action_index_in_plan += 1;
         if self.current_action_index_in_plan >= self.plan.len() {
             self.reset_plan();
@@ -45,8 +115,10 @@ impl AgentComponent {
     }
 
     pub fn reset_plan(&mut self) {
+        self.action_ticks = 0;
         self.current_action_index_in_plan = 0;
         self.plan.clear();
+        self.planner = None;
         self.goal = None;
     }
 
@@ -58,6 +130,49 @@ impl AgentComponent {
         !self.plan.is_empty()
     }
 
+    pub fn planner(&self) -> Option<&PlannerState> {
+        self.planner.as_ref()
+    }
+
+    pub fn planner_mut(&mut self) -> Option<&mut PlannerState> {
+        self.planner.as_mut()
+    }
+
+    pub fn take_planner(&mut self) -> Option<PlannerState> {
+        self.planner.take()
+    }
+
+    pub fn set_planner(&mut self, planner: PlannerState) {
+        self.planner = Some(planner);
+    }
+
+    pub fn agenda(&self) -> Option<&Agenda> {
+        self.agenda.as_ref()
+    }
+
+    /// Whether the agent is pursuing the goal, either directly or through the agenda it comes from
+    pub fn pursues_goal(&self, goal: usize) -> bool {
+        self.goal == Some(goal)
+            || self
+                .agenda
+                .as_ref()
+                .is_some_and(|agenda| agenda.goal() == goal && !agenda.is_empty())
+    }
+
+    pub fn take_agenda(&mut self) -> Option<Agenda> {
+        self.agenda.take()
+    }
+
+    pub fn set_agenda(&mut self, agenda: Agenda) {
+        self.agenda = Some(agenda);
+    }
+
+    /// Reset the plan and drop the remaining tasks of the agenda
+    pub fn abandon_agenda(&mut self) {
+        self.reset_plan();
+        self.agenda = None;
+    }
+
     pub fn goal_set(&self) -> usize {
         self.goal_set
     }
@@ -73,30 +188,53 @@ impl AgentComponent {
         self.action_set
     }
 
-    pub fn increase_action_cost(&mut self, action: usize) {
-        if action < self.action_costs.len() {
-            self.action_costs[action] += 1.0;
-        } else {
-            error!("No action with index {action}");
+    /// Count one more tick spent performing the current action
+    pub fn tick_action(&mut self, energy: f32) {
+        if self.action_ticks == 0 {
+            self.action_start_energy = energy;
         }
+        self.action_ticks += 1;
     }
 
-    pub fn reset_action_cost(&mut self, action: usize) {
-        if action < self.action_costs.len() {
-            self.action_costs[action] = 1.0;
+    /// Learn from the outcome of the current action, once it is over
+    pub fn record_action_outcome(
+        &mut self,
+        action: usize,
+        success: bool,
+        energy: f32,
+        config: &AgentConfig,
+    ) {
+        // Energy gained (e.g. by eating) does not make an action cheaper than its base cost
+        let energy_spent = f32::max(self.action_start_energy - energy, 0.0);
+        if let Some(stats) = self.action_stats.get_mut(action) {
+            stats.record(
+                self.action_ticks as f64,
+                energy_spent as f64,
+                success,
+                config,
+            );
         } else {
             error!("No action with index {action}");
         }
+        self.action_ticks = 0;
     }
 
-    pub fn get_action_cost(&self, action: usize) -> f64 {
-        if action < self.action_costs.len() {
-            self.action_costs[action]
-        } else {
-            1.0
+    /// New agent of the same goal and action sets
+    pub fn offspring(&self) -> Self {
+        AgentComponent::new(self.goal_set, self.action_set, self.action_stats.len())
+    }
+
+    /// Start with the knowledge of the parent about its actions
+    pub fn inherit_action_stats(&mut self, parent: &AgentComponent) {
+        if parent.action_set == self.action_set {
+            self.action_stats = parent.action_stats.clone();
         }
     }
 
+    pub fn get_action_cost(&self, action: usize) -> f64 {
+        self.action_stats.get(action).map_or(1.0, |s| s.cost())
+    }
+
     pub fn idle(&self) -> bool {
         self.idle
     }
@@ -125,9 +263,86 @@ impl AgentComponent {
                     desc.push(format!("    unknown action {action}"));
                 }
             }
+        } else if let Some(planner) = &self.planner {
+            desc.push(format!(
+                "    planning ({} nodes expanded, waited {} ticks)",
+                planner.nb_expanded_nodes(),
+                planner.nb_ticks_waited()
+            ));
         } else {
             desc.push("    none".to_string());
         }
+        desc.push("ACTION COSTS".to_string());
+        for (action, stats) in self.action_stats.iter().enumerate() {
+            let description = goap
+                .get_description(self.action_set(), action)
+                .unwrap_or_else(|| format!("unknown action {action}"));
+            desc.push(format!("    {description}: {}", stats.description()));
+        }
+        if let Some(agenda) = &self.agenda
+            && !agenda.is_empty()
+        {
+            desc.push("AGENDA".to_string());
+            for task in agenda.tasks() {
+                desc.push(format!(
+                    "    {}",
+                    goap.get_task_description(self.action_set(), task)
+                ));
+            }
+        }
         desc
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{components::agent_component::AgentComponent, configuration::test_config};
+
+    #[test]
+    fn test_action_cost_learning() {
+        let config = test_config().agent;
+        let is_close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        let mut agent = AgentComponent::new(0, 0, 2);
+        assert_eq!(agent.get_action_cost(0), 1.0);
+
+        // 10 ticks spent and 2 energy spent
+        for _ in 0..10 {
+            agent.tick_action(10.0);
+        }
+        agent.record_action_outcome(0, true, 8.0, &config);
+        let success_cost =
+            1.0 + 10.0 * config.action_cost_ticks_factor + 2.0 * config.action_cost_energy_factor;
+        assert!(is_close(agent.get_action_cost(0), success_cost));
+
+        // A failure lowers the success rate, that divides the cost
+        for _ in 0..10 {
+            agent.tick_action(8.0);
+        }
+        agent.record_action_outcome(0, false, 6.0, &config);
+        let success_rate = f64::max(
+            1.0 - config.action_cost_learning_rate,
+            config.action_min_success_rate,
+        );
+        let failure_cost = success_cost / success_rate;
+        assert!(is_close(agent.get_action_cost(0), failure_cost));
+
+        // The cost goes back down progressively with successes
+        for _ in 0..10 {
+            agent.tick_action(6.0);
+        }
+        agent.record_action_outcome(0, true, 4.0, &config);
+        assert!(success_cost < agent.get_action_cost(0) && agent.get_action_cost(0) < failure_cost);
+
+        // Energy gained does not lower the cost
+        agent.tick_action(0.0);
+        agent.record_action_outcome(1, true, 50.0, &config);
+        assert!(is_close(
+            agent.get_action_cost(1),
+            1.0 + config.action_cost_ticks_factor
+        ));
+
+        let mut offspring = AgentComponent::new(0, 0, 2);
+        offspring.inherit_action_stats(&agent);
+        assert_eq!(offspring.get_action_cost(0), agent.get_action_cost(0));
+    }
+}
@@ -1,11 +1,21 @@
-use crate::configuration::{Config, CreatureConfig};
-use crate::ecs::Component;
+use crate::algorithms::rng;
+use crate::configuration::{Config, CreatureConfig, SpeciesConfig};
+use crate::ecs::{Component, EntityId};
 use std::collections::VecDeque;
+use std::f64::consts::PI;
 
 #[derive(Clone)]
 pub struct CreatureComponent {
     pub energy: f32,
     pub health: f32,
+    // Grows with movement, recovered by sleeping
+    pub fatigue: f32,
+    // Grows with time, quenched by drinking
+    pub thirst: f32,
+    // Creature that wounded it last, forgotten when fully healed
+    pub last_attacker: Option<EntityId>,
+    // Creature whose attack brought its health to zero
+    pub killer: Option<EntityId>,
 }
 impl Component for CreatureComponent {}
 impl CreatureComponent {
@@ -13,6 +23,10 @@ impl CreatureComponent {
         Self {
             energy: config.start_energy,
             health: config.max_health,
+            fatigue: 0.0,
+            thirst: 0.0,
+            last_attacker: None,
+            killer: None,
         }
     }
 }
@@ -85,22 +99,109 @@ impl PlantComponent {
     }
 }
 
+// Marker of sleeping creatures
 #[derive(Clone)]
-pub struct CorpseComponent;
+pub struct SleepingComponent;
+impl Component for SleepingComponent {}
+
+#[derive(Clone)]
+pub struct CorpseComponent {
+    // Creature the corpse comes from, and the one that killed it (if any)
+    pub creature: Option<EntityId>,
+    pub killer: Option<EntityId>,
+    // Energy left to eat, lost as the corpse decays
+    pub energy: f32,
+}
 impl Component for CorpseComponent {}
 impl CorpseComponent {
+    pub fn new(energy: f32) -> Self {
+        Self {
+            creature: None,
+            killer: None,
+            energy,
+        }
+    }
+
+    pub fn of(creature: EntityId, killer: Option<EntityId>, energy: f32) -> Self {
+        Self {
+            creature: Some(creature),
+            killer,
+            energy,
+        }
+    }
+}
+
+// Species of a creature, as an index in the species of the configuration
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SpeciesComponent {
+    pub species: usize,
+}
+impl Component for SpeciesComponent {}
+impl SpeciesComponent {
+    pub fn new(species: usize) -> Self {
+        Self { species }
+    }
+
+    pub fn config<'c>(&self, config: &'c Config) -> &'c SpeciesConfig {
+        &config.species[self.species]
+    }
+}
+
+// Member of a herd, steered by its neighbours while moving
+#[derive(Clone, Copy)]
+pub struct HerdComponent {
+    // Direction of the last move (normalized), null when motionless
+    pub heading: (f64, f64),
+}
+impl Component for HerdComponent {}
+impl HerdComponent {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            heading: (0.0, 0.0),
+        }
+    }
+}
+
+// Direction a creature is facing, at the center of its field of view
+#[derive(Clone, Copy)]
+pub struct PerceptionComponent {
+    // Angle in radians
+    pub heading: f64,
+    // Sees as far at night as during the day
+    pub night_vision: bool,
+}
+impl Component for PerceptionComponent {}
+impl PerceptionComponent {
+    pub fn new(night_vision: bool) -> Self {
+        Self {
+            heading: rng::random_range(-PI, PI),
+            night_vision,
+        }
+    }
+
+    /// Check if the direction is within the field of view
+    pub fn faces(&self, (dx, dy): (f64, f64), field_of_view: f64) -> bool {
+        if field_of_view >= 2.0 * PI || (dx == 0.0 && dy == 0.0) {
+            return true;
+        }
+        // Angle between the heading and the direction, in [-PI; PI]
+        let angle = (dy.atan2(dx) - self.heading + PI).rem_euclid(2.0 * PI) - PI;
+        angle.abs() <= field_of_view / 2.0
+    }
+
+    /// Turn to look at what was outside the field of view
+    pub fn look_around(&mut self, field_of_view: f64) {
+        self.heading = (self.heading + field_of_view.max(PI / 4.0) + PI).rem_euclid(2.0 * PI) - PI;
     }
 }
 
 #[derive(Clone)]
-pub struct HerbivorousComponent {
+pub struct DigestionComponent {
     // Queue of (number of seeds, countdown to excretion)
     pub seeds: VecDeque<(usize, usize)>,
 }
-impl Component for HerbivorousComponent {}
-impl HerbivorousComponent {
+impl Component for DigestionComponent {}
+impl DigestionComponent {
     pub fn new() -> Self {
         Self {
             seeds: VecDeque::new(),
@@ -108,14 +209,10 @@ impl HerbivorousComponent {
     }
 }
 
+// Water source, where creatures drink
 #[derive(Clone)]
-pub struct CarnivorousComponent {}
-impl Component for CarnivorousComponent {}
-impl CarnivorousComponent {
-    pub fn new() -> Self {
-        Self {}
-    }
-}
+pub struct WaterComponent;
+impl Component for WaterComponent {}
 
 #[derive(Clone)]
 pub struct ObstacleComponent {}
@@ -136,3 +233,30 @@ impl MoveToTargetResultComponent {
         Self { success }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::components::all::PerceptionComponent;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_field_of_view() {
+        let perception = PerceptionComponent {
+            heading: PI - 0.1,
+            night_vision: false,
+        };
+        // The angles wrap around: behind the heading (near -PI) is in front of it
+        assert!(perception.faces(((-PI + 0.1).cos(), (-PI + 0.1).sin()), PI / 2.0));
+        assert!(perception.faces((-1.0, 0.0), PI / 2.0));
+        assert!(!perception.faces((1.0, 0.0), PI / 2.0));
+        assert!(!perception.faces((0.0, -1.0), PI / 2.0));
+        assert!(perception.faces((0.0, 1.0), PI + 0.3));
+
+        // A field of view of a full turn (or more) sees everywhere
+        assert!(perception.faces((1.0, 0.0), 2.0 * PI));
+        assert!(perception.faces((1.0, 0.0), 3.0 * PI));
+
+        // What is at the position of the creature is always seen
+        assert!(perception.faces((0.0, 0.0), 0.0));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::algorithms::rng;
 use crate::ecs::Component;
 use crate::ecs::EntityId;
+use crate::shared_data::biome;
 use crate::shared_data::body_grid;
 
 #[derive(Clone, Copy)]
@@ -24,7 +25,7 @@ pub struct BodyComponent {
 
 impl Component for BodyComponent {
     fn on_create(&mut self, entity: EntityId) {
-        // Generate a random position that does not collides with any already existing body
+        // Generate a random position on land, that does not collides with any already existing body
         if self.init_with_random_pos {
             loop {
                 let x = rng::random_range(
@@ -37,7 +38,7 @@ impl Component for BodyComponent {
                 );
                 self.x = x;
                 self.y = y;
-                if !body_grid::collides(entity, self) {
+                if !biome::is_water(x, y) && !body_grid::collides(entity, self) {
                     break;
                 }
             }
@@ -151,7 +152,12 @@ impl BodyComponent {
         self.is_traversable = traversable;
     }
 
+    /// Move the body, unless it collides or goes from land into water
     pub fn try_translate(&mut self, entity: EntityId, offset_x: f64, offset_y: f64) -> bool {
+        if biome::is_water(self.x + offset_x, self.y + offset_y) && !biome::is_water(self.x, self.y)
+        {
+            return false;
+        }
         if body_grid::try_translate(entity, self, offset_x, offset_y) {
             self.x += offset_x;
             self.y += offset_y;
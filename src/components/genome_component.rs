@@ -0,0 +1,154 @@
+use crate::algorithms::rng;
+use crate::configuration::{CreatureConfig, GenomeConfig, SpeciesConfig};
+use crate::ecs::Component;
+use std::f64::consts::PI;
+
+/// Heritable traits of a creature, mutated at birth
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GenomeComponent {
+    pub speed: f64,
+    pub size: f64,
+    pub hunger_rate: f32,
+    // Distance under which entities are seen
+    pub perception_radius: f64,
+    // Angle (in radians) of the vision cone, centered on the heading of the creature
+    pub field_of_view: f64,
+    pub reprod_energy_threshold: f32,
+}
+impl Component for GenomeComponent {}
+impl GenomeComponent {
+    /// Genome with the traits given by the configuration of the species
+    pub fn new(config: &CreatureConfig, species: &SpeciesConfig) -> Self {
+        Self {
+            speed: species.speed,
+            size: species.size,
+            hunger_rate: species.hunger_rate,
+            perception_radius: config.perception_radius,
+            field_of_view: species.field_of_view.to_radians(),
+            reprod_energy_threshold: config.reprod_energy_threshold,
+        }
+    }
+
+    /// Genome of an offspring: each trait comes from one of the parents, then is mutated.
+    /// Traits are kept within a range around the traits of the base genome, and the reproduction
+    /// threshold within the given bounds.
+    pub fn offspring(
+        &self,
+        other_parent: &GenomeComponent,
+        base: &GenomeComponent,
+        config: &GenomeConfig,
+        reprod_energy_threshold_bounds: (f32, f32),
+    ) -> Self {
+        let mut genome = Self {
+            speed: pick(self.speed, other_parent.speed),
+            size: pick(self.size, other_parent.size),
+            hunger_rate: pick(self.hunger_rate, other_parent.hunger_rate),
+            perception_radius: pick(self.perception_radius, other_parent.perception_radius),
+            field_of_view: pick(self.field_of_view, other_parent.field_of_view),
+            reprod_energy_threshold: pick(
+                self.reprod_energy_threshold,
+                other_parent.reprod_energy_threshold,
+            ),
+        };
+        genome.mutate(base, config);
+
+        // A creature must keep enough energy to reproduce
+        let (min_threshold, max_threshold) = reprod_energy_threshold_bounds;
+        genome.reprod_energy_threshold = genome
+            .reprod_energy_threshold
+            .clamp(min_threshold, max_threshold);
+        genome
+    }
+
+    /// Apply a gaussian noise to each trait, proportional to the trait value
+    pub fn mutate(&mut self, base: &GenomeComponent, config: &GenomeConfig) {
+        self.speed = mutate_trait(self.speed, base.speed, config);
+        self.size = mutate_trait(self.size, base.size, config);
+        self.hunger_rate =
+            mutate_trait(self.hunger_rate as f64, base.hunger_rate as f64, config) as f32;
+        self.perception_radius =
+            mutate_trait(self.perception_radius, base.perception_radius, config);
+        // Creatures cannot see more than all around them
+        self.field_of_view =
+            mutate_trait(self.field_of_view, base.field_of_view, config).min(2.0 * PI);
+        self.reprod_energy_threshold = mutate_trait(
+            self.reprod_energy_threshold as f64,
+            base.reprod_energy_threshold as f64,
+            config,
+        ) as f32;
+    }
+
+    pub fn description(&self) -> Vec<String> {
+        vec![
+            "GENOME".to_string(),
+            format!("    speed {:.2}", self.speed),
+            format!("    size {:.1}", self.size),
+            format!("    hunger rate {:.4}", self.hunger_rate),
+            format!("    perception radius {:.0}", self.perception_radius),
+            format!("    field of view {:.0}°", self.field_of_view.to_degrees()),
+            format!(
+                "    reproduction threshold {:.1}",
+                self.reprod_energy_threshold
+            ),
+        ]
+    }
+}
+
+fn pick<T>(a: T, b: T) -> T {
+    if rng::random_range(0.0, 1.0) < 0.5 {
+        a
+    } else {
+        b
+    }
+}
+
+fn mutate_trait(value: f64, base: f64, config: &GenomeConfig) -> f64 {
+    let mutated = value * (1.0 + rng::random_normal(0.0, config.mutation_std_dev));
+    mutated.clamp(
+        base * config.min_trait_factor,
+        base * config.max_trait_factor,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        algorithms::rng, components::genome_component::GenomeComponent, configuration::GenomeConfig,
+    };
+
+    #[test]
+    fn test_mutation_bounds() {
+        rng::init_with_seed(1);
+        let base = GenomeComponent {
+            speed: 1.5,
+            size: 30.0,
+            hunger_rate: 0.025,
+            perception_radius: 300.0,
+            field_of_view: 4.0,
+            reprod_energy_threshold: 90.0,
+        };
+        let bounds = (50.0, 100.0);
+        let mut config = GenomeConfig {
+            mutation_std_dev: 0.5,
+            min_trait_factor: 0.5,
+            max_trait_factor: 1.5,
+        };
+
+        let mut genome = base;
+        for _ in 0..1000 {
+            genome = genome.offspring(&base, &base, &config, bounds);
+            assert!(genome.speed >= base.speed * config.min_trait_factor);
+            assert!(genome.speed <= base.speed * config.max_trait_factor);
+            assert!(genome.size >= base.size * config.min_trait_factor);
+            assert!(genome.size <= base.size * config.max_trait_factor);
+            assert!(genome.field_of_view <= 2.0 * std::f64::consts::PI);
+            assert!(genome.reprod_energy_threshold >= bounds.0);
+            assert!(genome.reprod_energy_threshold <= bounds.1);
+        }
+
+        // Without mutation, the traits come from the parents
+        config.mutation_std_dev = 0.0;
+        let offspring = genome.offspring(&genome, &base, &config, bounds);
+        assert_eq!(offspring, genome);
+    }
+}
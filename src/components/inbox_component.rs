@@ -0,0 +1,138 @@
+use crate::components::memory_component::MemoryKind;
+use crate::ecs::{Component, EntityId};
+
+// Food and water sources reported to the others
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoodKind {
+    Plant,
+    Corpse,
+    Water,
+}
+impl FoodKind {
+    /// Kind of the food sources remembered as the given kind (None if not reported)
+    pub fn from_memory(kind: MemoryKind) -> Option<Self> {
+        match kind {
+            MemoryKind::Plant => Some(FoodKind::Plant),
+            MemoryKind::Corpse => Some(FoodKind::Corpse),
+            MemoryKind::Water => Some(FoodKind::Water),
+            _ => None,
+        }
+    }
+
+    pub fn memory_kind(&self) -> MemoryKind {
+        match self {
+            FoodKind::Plant => MemoryKind::Plant,
+            FoodKind::Corpse => MemoryKind::Corpse,
+            FoodKind::Water => MemoryKind::Water,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    // A predator has been seen (the subject of the message)
+    Alarm,
+    // Some food or water (of the given kind) has been found
+    FoodFound(FoodKind),
+    // The sender looks for a mate
+    MatingCall,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Message {
+    pub signal: Signal,
+    pub sender: EntityId,
+    // Entity the message is about (the sender itself for a mating call)
+    pub subject: EntityId,
+    // Position of the subject when the message was sent
+    pub x: f64,
+    pub y: f64,
+    // Number of ticks since the message was received
+    pub age: usize,
+}
+
+/// Messages received by a creature from the creatures of its species nearby
+#[derive(Clone)]
+pub struct InboxComponent {
+    messages: Vec<Message>,
+}
+impl Component for InboxComponent {}
+impl InboxComponent {
+    pub fn new() -> Self {
+        Self {
+            messages: Vec::new(),
+        }
+    }
+
+    /// Receive a message, replacing the previous one about the same subject (if any)
+    pub fn receive(&mut self, message: Message) {
+        self.messages
+            .retain(|m| m.signal != message.signal || m.subject != message.subject);
+        self.messages.push(message);
+    }
+
+    pub fn messages(&self, signal: Signal) -> impl Iterator<Item = &Message> {
+        self.messages.iter().filter(move |m| m.signal == signal)
+    }
+
+    /// Messages received less than the given number of ticks ago
+    pub fn recent_messages(&self, ticks: usize) -> impl Iterator<Item = &Message> {
+        self.messages.iter().filter(move |m| m.age < ticks)
+    }
+
+    /// Make the messages older, discarding the ones older than the time to live
+    pub fn decay(&mut self, ttl_ticks: usize) {
+        for message in self.messages.iter_mut() {
+            message.age += 1;
+        }
+        self.messages.retain(|m| m.age <= ttl_ticks);
+    }
+
+    pub fn description(&self) -> Vec<String> {
+        let mut description = vec!["INBOX".to_string()];
+        description.extend(self.messages.iter().map(|m| {
+            format!(
+                "    {:?} from {} at ({:.0}, {:.0}), {} ticks ago",
+                m.signal, m.sender, m.x, m.y, m.age
+            )
+        }));
+        description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::inbox_component::{InboxComponent, Message, Signal};
+
+    fn message(signal: Signal, subject: usize, x: f64) -> Message {
+        Message {
+            signal,
+            sender: 0,
+            subject,
+            x,
+            y: 0.0,
+            age: 0,
+        }
+    }
+
+    #[test]
+    fn test_inbox() {
+        let mut inbox = InboxComponent::new();
+        inbox.receive(message(Signal::Alarm, 1, 0.0));
+        inbox.decay(2);
+        inbox.receive(message(Signal::MatingCall, 2, 0.0));
+
+        // A new message about the same subject replaces the previous one
+        inbox.receive(message(Signal::Alarm, 1, 10.0));
+        let alarms: Vec<f64> = inbox.messages(Signal::Alarm).map(|m| m.x).collect();
+        assert_eq!(alarms, vec![10.0]);
+
+        inbox.decay(2);
+        inbox.decay(2);
+        assert_eq!(inbox.recent_messages(3).count(), 2);
+        assert_eq!(inbox.recent_messages(2).count(), 0);
+        inbox.decay(2);
+        assert_eq!(inbox.messages(Signal::Alarm).count(), 0);
+        assert_eq!(inbox.messages(Signal::MatingCall).count(), 0);
+    }
+}
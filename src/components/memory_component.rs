@@ -0,0 +1,168 @@
+use crate::configuration::MemoryConfig;
+use crate::ecs::{Component, EntityId};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryKind {
+    Plant,
+    Corpse,
+    Prey,
+    Water,
+    Predator,
+    // Creature of the same species looking for a mate
+    Mate,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryEntry {
+    pub kind: MemoryKind,
+    pub entity: EntityId,
+    // Position where the entity was last seen
+    pub x: f64,
+    pub y: f64,
+    // Number of ticks since the entity was last seen
+    pub age: usize,
+}
+
+/// Locations of the food sources, prey and predators seen by a creature (or reported by others).
+/// Memories decay: they are forgotten when too old, or when the entity is no longer seen where
+/// it was remembered.
+#[derive(Clone)]
+pub struct MemoryComponent {
+    entries: Vec<MemoryEntry>,
+}
+impl Component for MemoryComponent {}
+impl MemoryComponent {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &[MemoryEntry] {
+        &self.entries
+    }
+
+    /// Remember (or refresh) the location of an entity seen right now.
+    /// When the memory is full, the oldest memory is forgotten.
+    pub fn remember(
+        &mut self,
+        kind: MemoryKind,
+        entity: EntityId,
+        (x, y): (f64, f64),
+        config: &MemoryConfig,
+    ) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.entity == entity) {
+            *entry = MemoryEntry {
+                kind,
+                entity,
+                x,
+                y,
+                age: 0,
+            };
+            return;
+        }
+        if self.entries.len() >= config.max_entries
+            && let Some(oldest) = (0..self.entries.len()).max_by_key(|&i| self.entries[i].age)
+        {
+            self.entries.swap_remove(oldest);
+        }
+        self.entries.push(MemoryEntry {
+            kind,
+            entity,
+            x,
+            y,
+            age: 0,
+        });
+    }
+
+    pub fn forget(&mut self, entity: EntityId) {
+        self.entries.retain(|e| e.entity != entity);
+    }
+
+    /// Make the memories older, forgetting the ones that are too old
+    pub fn decay(&mut self, ticks: usize, config: &MemoryConfig) {
+        for entry in self.entries.iter_mut() {
+            entry.age += ticks;
+        }
+        self.entries.retain(|e| e.age <= config.max_age_ticks);
+    }
+
+    /// Return the remembered entities of the given kind, closest first
+    pub fn recall(&self, kind: MemoryKind, (x, y): (f64, f64)) -> Vec<MemoryEntry> {
+        let mut entries: Vec<MemoryEntry> = self
+            .entries
+            .iter()
+            .filter(|e| e.kind == kind)
+            .copied()
+            .collect();
+        entries.sort_by(|a, b| {
+            let distance_a = (a.x - x).powi(2) + (a.y - y).powi(2);
+            let distance_b = (b.x - x).powi(2) + (b.y - y).powi(2);
+            distance_a.total_cmp(&distance_b)
+        });
+        entries
+    }
+
+    /// Check if a predator was seen near the position
+    pub fn is_dangerous(&self, (x, y): (f64, f64), config: &MemoryConfig) -> bool {
+        self.entries.iter().any(|e| {
+            e.kind == MemoryKind::Predator
+                && (e.x - x).powi(2) + (e.y - y).powi(2) < config.danger_distance.powi(2)
+        })
+    }
+
+    pub fn description(&self) -> Vec<String> {
+        let mut description = vec!["MEMORY".to_string()];
+        description.extend(self.entries.iter().map(|e| {
+            format!(
+                "    {:?} at ({:.0}, {:.0}), {} ticks ago",
+                e.kind, e.x, e.y, e.age
+            )
+        }));
+        description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        components::memory_component::{MemoryComponent, MemoryKind},
+        configuration::MemoryConfig,
+    };
+
+    #[test]
+    fn test_memory() {
+        let config = MemoryConfig {
+            perception_period_ticks: 10,
+            max_age_ticks: 100,
+            max_entries: 3,
+            danger_distance: 50.0,
+        };
+        let mut memory = MemoryComponent::new();
+        memory.remember(MemoryKind::Plant, 1, (100.0, 0.0), &config);
+        memory.decay(10, &config);
+        memory.remember(MemoryKind::Plant, 2, (10.0, 0.0), &config);
+        memory.remember(MemoryKind::Corpse, 3, (0.0, 10.0), &config);
+
+        // Closest first, other kinds ignored
+        let plants: Vec<usize> = memory
+            .recall(MemoryKind::Plant, (0.0, 0.0))
+            .iter()
+            .map(|e| e.entity)
+            .collect();
+        assert_eq!(plants, vec![2, 1]);
+
+        // When full, the oldest memory is forgotten
+        memory.remember(MemoryKind::Predator, 4, (0.0, 0.0), &config);
+        assert_eq!(memory.recall(MemoryKind::Plant, (0.0, 0.0)).len(), 1);
+        assert!(memory.is_dangerous((10.0, 10.0), &config));
+        assert!(!memory.is_dangerous((100.0, 0.0), &config));
+
+        // Seeing an entity again refreshes its memory
+        memory.decay(90, &config);
+        memory.remember(MemoryKind::Plant, 2, (10.0, 0.0), &config);
+        memory.decay(20, &config);
+        assert_eq!(memory.entries().len(), 1);
+        assert_eq!(memory.entries()[0].entity, 2);
+    }
+}
@@ -1,4 +1,8 @@
+pub mod age_component;
 pub mod agent_component;
 pub mod all;
 pub mod body_component;
+pub mod genome_component;
+pub mod inbox_component;
+pub mod memory_component;
 pub mod move_to_target_component;
@@ -3,6 +3,17 @@ use crate::components::body_component::BodyComponent;
 use crate::configuration::Config;
 use crate::ecs::{Component, EntityId};
 
+// Weight of the last move of the target in its estimated velocity
+const VELOCITY_SMOOTHING: f64 = 0.5;
+
+/// State of the chase of a moving target
+#[derive(Clone, Copy)]
+pub struct Pursuit {
+    // Estimated velocity of the target (distance per tick)
+    pub velocity: (f64, f64),
+    pub chase_ticks: usize,
+}
+
 #[derive(Clone)]
 pub struct MoveToTargetComponent {
     target_entity: EntityId,
@@ -10,6 +21,7 @@ pub struct MoveToTargetComponent {
     path_to_target: Vec<WayPoint>,
     graph: Graph,
     speed: f64,
+    pursuit: Option<Pursuit>,
 }
 
 impl Component for MoveToTargetComponent {}
@@ -27,7 +39,37 @@ impl MoveToTargetComponent {
             path_to_target,
             graph: Graph::new(),
             speed,
+            pursuit: None,
+        }
+    }
+
+    /// Chase the target, anticipating its moves
+    pub fn with_pursuit(mut self) -> Self {
+        self.pursuit = Some(Pursuit {
+            velocity: (0.0, 0.0),
+            chase_ticks: 0,
+        });
+        self
+    }
+
+    pub fn pursuit(&self) -> Option<&Pursuit> {
+        self.pursuit.as_ref()
+    }
+
+    /// Update the position of the target (and the estimation of its velocity, if chased)
+    pub fn update_target(&mut self, target_body: BodyComponent) {
+        if let Some(pursuit) = &mut self.pursuit {
+            let (dx, dy) = (
+                target_body.x() - self.target_body.x(),
+                target_body.y() - self.target_body.y(),
+            );
+            pursuit.velocity = (
+                VELOCITY_SMOOTHING * dx + (1.0 - VELOCITY_SMOOTHING) * pursuit.velocity.0,
+                VELOCITY_SMOOTHING * dy + (1.0 - VELOCITY_SMOOTHING) * pursuit.velocity.1,
+            );
+            pursuit.chase_ticks += 1;
         }
+        self.target_body = target_body;
     }
 
     // TODO maybe refactor this and call compute_path in caller only ? or maybe not
@@ -81,8 +123,9 @@ impl MoveToTargetComponent {
         &self.target_body
     }
 
-    pub fn target_body_mut(&mut self) -> &mut BodyComponent {
-        &mut self.target_body
+    /// Position of the end of the path (if any)
+    pub fn path_end(&self) -> Option<(f64, f64)> {
+        self.path_to_target.last().map(|wp| (wp.x(), wp.y()))
     }
 
     pub fn speed(&self) -> f64 {
@@ -1,13 +1,13 @@
 use serde::Deserialize;
 use std::fs;
 
-#[derive(Deserialize, Clone, Copy)]
+use crate::goals::utility_curves::{UtilityCurve, UtilityInput};
+
+#[derive(Deserialize, Clone)]
 pub struct Config {
     pub rng_seed: u64,
     pub body_domain_initial_width: f64,
     pub body_domain_initial_height: f64,
-    pub herbivorous_nb: usize,
-    pub carnivorous_nb: usize,
     pub obstacle_nb: usize,
     pub corpse_nb: usize,
     pub plant_nb: usize,
@@ -15,8 +15,21 @@ pub struct Config {
     pub obstacle_size: f64,
     pub seed: SeedConfig,
     pub plant: PlantConfig,
+    pub water: WaterConfig,
     pub agent: AgentConfig,
     pub creature: CreatureConfig,
+    pub genome: GenomeConfig,
+    pub memory: MemoryConfig,
+    pub messaging: MessagingConfig,
+    pub pursuit: PursuitConfig,
+    pub combat: CombatConfig,
+    pub aging: AgingConfig,
+    pub corpse: CorpseConfig,
+    pub soil: SoilConfig,
+    pub climate: ClimateConfig,
+    pub day: DayConfig,
+    pub biome: BiomeConfig,
+    pub species: Vec<SpeciesConfig>,
     pub path: PathConfig,
     pub collision: CollisionConfig,
     pub renderer: RendererConfig,
@@ -40,31 +53,373 @@ pub struct PlantConfig {
     pub energy_per_size_unit: f32,
 }
 
+// Water sources, placed in the most humid places
+#[derive(Deserialize, Clone, Copy)]
+pub struct WaterConfig {
+    pub nb: usize,
+    // Must not be bigger than the cells of the body grid
+    pub size: f64,
+    // Water sources are only placed where the humidity is above this level
+    pub min_humidity: f64,
+    // Number of random positions tried to place all the water sources
+    pub max_placement_attempts: usize,
+    // Distance between the water sources placed along the shores of the lakes, and their size
+    pub shore_spacing: f64,
+    pub shore_size: f64,
+}
+
 #[derive(Deserialize, Clone, Copy)]
 pub struct AgentConfig {
     pub total_ticks_idle: usize,
+    // Number of planner nodes that can be expanded per tick, shared by all agents (positive)
+    pub planning_budget_per_tick: usize,
+    // Record the decisions of the agents (exported with the X key)
+    pub decision_trace: bool,
+    pub decision_trace_max_records: usize,
+    // Dead agents are kept in the trace until this number of agents is reached
+    pub decision_trace_max_agents: usize,
+    // Weight of the most recent outcome in the averages of the measured action outcomes
+    pub action_cost_learning_rate: f64,
+    // Cost added per tick and per energy unit spent performing an action
+    pub action_cost_ticks_factor: f64,
+    pub action_cost_energy_factor: f64,
+    // Lower bound of the success rate, that divides the cost of an action
+    pub action_min_success_rate: f64,
+    // Offspring start with the action costs learnt by their parent
+    pub inherit_action_stats: bool,
+    // Utility gap above which another goal interrupts the current one
+    pub goal_interruption_margin: f32,
 }
 
 #[derive(Deserialize, Clone, Copy)]
 pub struct CreatureConfig {
-    pub size: f64,
-    pub hunger_rate: f32,
     pub exhaustion_rate: f32,
     pub recovery_rate: f32,
-    pub corpse_energy: f32,
     pub reprod_energy_threshold: f32,
     pub reprod_energy_cost: f32,
     pub start_energy: f32,
     pub max_energy: f32,
     pub max_health: f32,
-    pub herbivorous_ticks_to_digest: usize,
+    pub ticks_to_digest: usize,
     pub reprod_x_offset: f64,
-    pub herbivorous_speed: f64,
-    pub carnivorous_speed: f64,
+    pub reproduction_mode: ReproductionMode,
+    // Distance under which creatures see other entities (mutated in the genomes)
+    pub perception_radius: f64,
+    // Distance of the position creatures flee to
+    pub flee_distance: f64,
+    pub max_fatigue: f32,
+    // Fatigue gained per distance unit moved
+    pub fatigue_per_distance: f32,
+    // Fatigue under which a creature does not need to rest
+    pub rested_fatigue: f32,
+    // Fatigue recovered per tick of sleep
+    pub sleep_recovery_rate: f32,
+    // Perception distances are multiplied by this factor while sleeping
+    pub sleep_perception_factor: f64,
+    // Damage taken is multiplied by this factor while sleeping (a sleeping prey does not fight back)
+    pub sleep_damage_factor: f32,
+    // Thirst gained per tick
+    pub thirst_rate: f32,
+    pub max_thirst: f32,
+    // Thirst under which a creature does not need to drink
+    pub quenched_thirst: f32,
+    // Thirst quenched per tick of drinking
+    pub drink_rate: f32,
+    // Health lost per tick when the thirst is at its maximum
+    pub dehydration_rate: f32,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct GenomeConfig {
+    // Standard deviation of the mutations, relative to the trait value
+    pub mutation_std_dev: f64,
+    // Bounds of the traits, relative to the traits given by the creature configuration
+    pub min_trait_factor: f64,
+    pub max_trait_factor: f64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct MemoryConfig {
+    // Creatures update their memory of what they see every given number of ticks
+    pub perception_period_ticks: usize,
+    // Memories older than this are forgotten
+    pub max_age_ticks: usize,
+    pub max_entries: usize,
+    // Remembered food sources closer than this to a remembered predator are avoided
+    pub danger_distance: f64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct MessagingConfig {
+    // Messages older than this are discarded by their receivers
+    pub ttl_ticks: usize,
+    // Distance under which the creatures of the same species hear each signal
+    pub alarm_range: f64,
+    pub food_found_range: f64,
+    pub mating_call_range: f64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct PursuitConfig {
+    // Predators give up a chase after this number of ticks
+    pub max_chase_ticks: usize,
+    // Number of ticks the moves of the prey are anticipated
+    pub max_prediction_ticks: f64,
+    // The path to the prey is computed again when the prey is farther than this from its end
+    pub replan_distance: f64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct CombatConfig {
+    // Health removed per tick of attack, between creatures of the same size
+    // (multiplied by the size ratio of the attacker to its target)
+    pub attack_damage: f32,
+    // Health removed per tick from the attacker by its prey fighting back (same size ratio)
+    pub counter_damage: f32,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct AgingConfig {
+    // Fraction of the lifespan after which juveniles become adults
+    pub juvenile_end: f64,
+    // Fraction of the lifespan after which adults become elders
+    pub elder_start: f64,
+    // Standard deviation of the lifespans, relative to the lifespan of the species
+    pub lifespan_std_dev: f64,
+    // Size of the newborns, relative to their adult size
+    pub newborn_size_factor: f64,
+    // Speed of the juveniles and of the elders, relative to their adult speed
+    pub juvenile_speed_factor: f64,
+    pub elder_speed_factor: f64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct CorpseConfig {
+    // Energy of a corpse per size unit of the dead creature
+    pub energy_per_size: f32,
+    // Fraction of the energy left in the dead creature added to its corpse
+    pub remaining_energy_fraction: f32,
+    // Energy lost per tick by a corpse decaying, until it vanishes
+    pub decay_rate: f32,
+    // Nutrients given to the soil per unit of energy decayed
+    pub nutrients_per_energy: f64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct SoilConfig {
+    pub cell_size: f64,
+    // Nutrients of an untouched soil, where plants grow at their normal rate (positive)
+    pub base_nutrients: f64,
+    pub max_nutrients: f64,
+    // Nutrients per tick by which the soil goes back to the base level
+    pub regeneration_rate: f64,
+    // Nutrients consumed by plants per size unit grown
+    pub nutrients_per_size: f64,
+    // Growth rate and maximum size of the plants are multiplied by
+    // nutrients / base_nutrients, up to this factor
+    pub max_growth_factor: f64,
+    // Nutrients given to the soil by creatures excreting seeds
+    pub droppings_nutrients: f64,
+}
+
+// Cycle of the seasons. The amplitudes are relative to the mean values, in [0; 1[, except for
+// the temperature (in degrees).
+#[derive(Deserialize, Clone, Copy)]
+pub struct ClimateConfig {
+    // Duration of a full cycle of the four seasons (positive)
+    pub year_ticks: usize,
+    pub temperature_mean: f64,
+    pub temperature_amplitude: f64,
+    pub humidity_amplitude: f64,
+    pub growth_amplitude: f64,
+    pub seed_amplitude: f64,
+    pub hunger_amplitude: f64,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct DayConfig {
+    // Duration of a full day (the day then the night, positive)
+    pub day_ticks: usize,
+    // Perception distances are multiplied by this factor in the dark, in [0; 1]
+    // (except for the species active at night)
+    pub night_perception_factor: f64,
+    // Utility added to the rest goal of the creatures outside of their activity hours
+    pub off_hours_rest_utility: f32,
+    // Brightness of the scene in the dark, in [0; 1]
+    pub night_brightness: f64,
+}
+
+// Terrain generated from the elevation, temperature and humidity layers (all in [0; 1])
+#[derive(Deserialize, Clone, Copy)]
+pub struct BiomeConfig {
+    // Locations below this elevation are water, which cannot be crossed
+    pub water_level: f64,
+    // Locations where the temperature exceeds the humidity by this margin are desert
+    pub desert_dryness: f64,
+    // Locations above this humidity are forest, the others are grassland
+    pub forest_humidity: f64,
+    // Speeds are divided by the cost of the biome crossed (at least 1, the path finding heuristic
+    // assumes it)
+    pub grassland_cost: f64,
+    pub forest_cost: f64,
+    pub desert_cost: f64,
+    // Distance between the points checked along the edges of the path finding graph
+    pub water_check_step: f64,
+    // The terrain is uniform within the cells of this size
+    pub cell_size: f64,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Activity {
+    // Active during the day, resting at night
+    Diurnal,
+    // Active at night (seeing in the dark), resting during the day
+    Nocturnal,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ReproductionMode {
+    // Creatures with enough energy spawn a clone next to them
+    Asexual,
+    // Creatures with enough energy look for a mate, both parents sharing the cost of the offspring
+    Mating,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Food {
+    Plant,
+    Corpse,
+    // Creatures of the species hunted
+    Prey,
+}
+
+// Kind of food edible by a species
+#[derive(Deserialize, Clone, Copy)]
+pub struct DietConfig {
+    pub food: Food,
+    // Preferred foods are cheaper to plan for (the cost of eating is divided by the preference)
+    pub preference: f64,
+    // Multiplies the energy given by the food
+    pub energy_yield: f32,
+}
+
+// Goals that can be pursued by the creatures of a species
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalKind {
+    ReplenishEnergy,
+    // Needs the flee utility of the species
+    Flee,
+    Rest,
+    // Needs the move to water and drink actions
+    Drink,
+    // Only used when reproducing by mating, needs the replenish energy goal and the mate actions
+    Reproduce,
+}
+
+// Actions that can be performed by the creatures of a species
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    // The move and eat actions need the food in the diet of the species
+    MoveToPlant,
+    EatPlant,
+    MoveToCorpse,
+    EatCorpse,
+    MoveToPrey,
+    Attack,
+    EatPrey,
+    Flee,
+    Sleep,
+    MoveToWater,
+    Drink,
+    // Only used when reproducing by mating
+    MoveToMate,
+    Mate,
+}
+
+impl ActionKind {
+    /// Food needed to perform the action, if any
+    pub fn food(&self) -> Option<Food> {
+        match self {
+            ActionKind::MoveToPlant | ActionKind::EatPlant => Some(Food::Plant),
+            ActionKind::MoveToCorpse | ActionKind::EatCorpse => Some(Food::Corpse),
+            ActionKind::MoveToPrey | ActionKind::Attack | ActionKind::EatPrey => Some(Food::Prey),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SpeciesConfig {
+    pub name: String,
+    pub diet: Vec<DietConfig>,
+    // Names of the species hunted
+    #[serde(default)]
+    pub prey: Vec<String>,
+    pub initial_nb: usize,
+    pub speed: f64,
+    pub size: f64,
+    pub hunger_rate: f32,
+    // Average age (in ticks) at which creatures die naturally
+    pub lifespan: usize,
+    // Angle (in degrees) of the vision cone (mutated in the genomes)
+    pub field_of_view: f64,
+    pub activity: Activity,
+    pub color: [u8; 4],
+    // Species without this configuration do not flock
+    pub herd: Option<HerdConfig>,
+    pub utility: SpeciesUtilityConfig,
+    // Goal set and action set of the creatures
+    pub goals: Vec<GoalKind>,
+    pub actions: Vec<ActionKind>,
+}
+
+impl SpeciesConfig {
+    pub fn diet(&self, food: Food) -> Option<&DietConfig> {
+        self.diet.iter().find(|d| d.food == food)
+    }
+}
+
+// Steering of the moving creatures by the members of their herd (creatures of the same species)
+#[derive(Deserialize, Clone, Copy)]
+pub struct HerdConfig {
+    // Distance under which the members of the herd are neighbours
+    pub radius: f64,
+    // Distance (center to center) under which neighbours push each other away
+    pub separation_distance: f64,
+    pub separation_weight: f64,
+    // Steering towards the average heading of the neighbours
+    pub alignment_weight: f64,
+    // Steering towards the center of the neighbours
+    pub cohesion_weight: f64,
+}
+
+// Utility of the goals of a species
+#[derive(Deserialize, Clone, Copy)]
+pub struct SpeciesUtilityConfig {
+    pub replenish_energy: GoalUtilityConfig,
+    // Species without this configuration never flee
+    pub flee: Option<GoalUtilityConfig>,
+    pub rest: GoalUtilityConfig,
+    pub drink: GoalUtilityConfig,
+    // Only used when reproducing by mating
+    pub reproduce: GoalUtilityConfig,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct GoalUtilityConfig {
+    pub weight: f32,
+    pub input: UtilityInput,
+    pub curve: UtilityCurve,
 }
 
 #[derive(Deserialize, Clone, Copy)]
 pub struct PathConfig {
+    // Upper bound of the distance of the targets searched (that must also be seen)
     pub max_search_distance: f64,
     pub nb_prm_positions_generated: usize,
 }
@@ -86,17 +441,29 @@ pub struct RendererConfig {
     pub bar_height: f64,
     pub grid_line_wideness: f64,
     pub graph_edge_thickness: f64,
+    // Size in pixels of the plots of the utility curves (debug view)
+    pub utility_plot_width: u32,
+    pub utility_plot_height: u32,
     pub color: RendererColorConfig,
 }
 
 #[derive(Deserialize, Clone, Copy)]
 pub struct RendererColorConfig {
     pub background_color: [u8; 4],
-    pub herbivorous_color: [u8; 4],
-    pub carnivorous_color: [u8; 4],
     pub plant_color: [u8; 4],
     pub energy_color: [u8; 4],
     pub health_color: [u8; 4],
+    pub fatigue_color: [u8; 4],
+    // Remembered predators of the selected agent
+    pub danger_color: [u8; 4],
+    // Vision cone of the selected agent
+    pub perception_color: [u8; 4],
+    pub nutrient_color: [u8; 4],
+    pub water_color: [u8; 4],
+    pub grassland_color: [u8; 4],
+    pub forest_color: [u8; 4],
+    pub desert_color: [u8; 4],
+    pub thirst_color: [u8; 4],
     pub corpse_color: [u8; 4],
     pub obstacle_color: [u8; 4],
     pub waypoint_color: [u8; 4],
@@ -106,11 +473,145 @@ pub struct RendererColorConfig {
     pub seed_color: [u8; 4],
     pub text_color: [u8; 4],
     pub text_background_color: [u8; 4],
+    pub utility_curve_color: [u8; 4],
+    pub utility_input_color: [u8; 4],
+}
+
+impl Config {
+    pub fn species_index(&self, name: &str) -> Option<usize> {
+        self.species.iter().position(|s| s.name == name)
+    }
+
+    /// Return the species hunting the given species
+    pub fn predators_of(&self, species: usize) -> Vec<usize> {
+        let name = &self.species[species].name;
+        self.species
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.prey.contains(name))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Return the species hunted by the given species
+    pub fn prey_of(&self, species: usize) -> Vec<usize> {
+        (0..self.species.len())
+            .filter(|&prey| self.is_prey_of(prey, species))
+            .collect()
+    }
+
+    pub fn is_prey_of(&self, prey: usize, predator: usize) -> bool {
+        self.species[predator]
+            .prey
+            .contains(&self.species[prey].name)
+    }
+
+    /// Energy of the corpse of a creature of the given size, dead with the given energy left
+    pub fn corpse_energy(&self, size: f64, remaining_energy: f32) -> f32 {
+        self.corpse.energy_per_size * size as f32
+            + self.corpse.remaining_energy_fraction * remaining_energy.max(0.0)
+    }
+
+    /// Size of the largest creature possible, taking the mutations into account
+    pub fn max_creature_size(&self) -> f64 {
+        self.species.iter().map(|s| s.size).fold(0.0, f64::max) * self.genome.max_trait_factor
+    }
 }
 
 pub fn load_config(file_name: &str) -> Config {
     let content = fs::read_to_string(file_name)
         .unwrap_or_else(|_| panic!("Failed to read configuration file {}", file_name));
-    toml::from_str(&content)
-        .unwrap_or_else(|_| panic!("Failed to parse configuration file {}", file_name))
+    let config: Config = toml::from_str(&content)
+        .unwrap_or_else(|_| panic!("Failed to parse configuration file {}", file_name));
+    if config.agent.planning_budget_per_tick == 0 {
+        panic!("The planning budget per tick must be positive");
+    }
+    if config.soil.base_nutrients <= 0.0 {
+        panic!("The base nutrients of the soil must be positive");
+    }
+    if config.climate.year_ticks == 0 {
+        panic!("The duration of a year must be positive");
+    }
+    for amplitude in [
+        config.climate.humidity_amplitude,
+        config.climate.growth_amplitude,
+        config.climate.seed_amplitude,
+        config.climate.hunger_amplitude,
+    ] {
+        if !(0.0..1.0).contains(&amplitude) {
+            panic!("The seasonal amplitudes must be in [0; 1[");
+        }
+    }
+    if config.day.day_ticks == 0 {
+        panic!("The duration of a day must be positive");
+    }
+    if !(0.0..=1.0).contains(&config.day.night_perception_factor)
+        || !(0.0..=1.0).contains(&config.day.night_brightness)
+    {
+        panic!("The night perception factor and brightness must be in [0; 1]");
+    }
+    if config.biome.grassland_cost < 1.0
+        || config.biome.forest_cost < 1.0
+        || config.biome.desert_cost < 1.0
+    {
+        panic!("The movement costs of the biomes must be at least 1");
+    }
+    if config.biome.cell_size <= 0.0 {
+        panic!("The size of the terrain cells must be positive");
+    }
+    if config.water.shore_spacing <= 0.0 {
+        panic!("The spacing of the water sources along the shores must be positive");
+    }
+    for species in config.species.iter() {
+        for prey in species.prey.iter() {
+            if config.species_index(prey).is_none() {
+                panic!("Unknown prey {} of species {}", prey, species.name);
+            }
+        }
+        if species.prey.is_empty() && species.diet(Food::Prey).is_some() {
+            panic!("Species {} eats prey but hunts no species", species.name);
+        }
+        if species.diet.iter().any(|d| d.preference <= 0.0) {
+            panic!(
+                "Species {} has a non positive food preference",
+                species.name
+            );
+        }
+        if species.goals.contains(&GoalKind::Flee) && species.utility.flee.is_none() {
+            panic!("Species {} flees without a flee utility", species.name);
+        }
+        if species.goals.contains(&GoalKind::Drink)
+            && (!species.actions.contains(&ActionKind::MoveToWater)
+                || !species.actions.contains(&ActionKind::Drink))
+        {
+            panic!("Species {} drinks without the drink actions", species.name);
+        }
+        if species.goals.contains(&GoalKind::Reproduce)
+            && (!species.goals.contains(&GoalKind::ReplenishEnergy)
+                || !species.actions.contains(&ActionKind::MoveToMate)
+                || !species.actions.contains(&ActionKind::Mate))
+        {
+            panic!(
+                "Species {} reproduces without replenishing energy or mating",
+                species.name
+            );
+        }
+        for action in species.actions.iter() {
+            if let Some(food) = action.food()
+                && species.diet(food).is_none()
+            {
+                panic!(
+                    "Species {} can {:?} without {:?} in its diet",
+                    species.name, action, food
+                );
+            }
+        }
+    }
+    config
+}
+
+/// Configuration of the repository, for the tests
+#[cfg(test)]
+pub fn test_config() -> Config {
+    toml::from_str(include_str!("../config.toml")).unwrap()
 }
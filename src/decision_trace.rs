@@ -0,0 +1,268 @@
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use crate::ecs::EntityId;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PlannedAction {
+    pub action: usize,
+    pub description: String,
+    pub cost: f64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DecisionEvent {
+    GoalChosen {
+        goal: usize,
+        // Utility of each goal of the goal set
+        utilities: Vec<f32>,
+    },
+    GoalInterrupted {
+        goal: usize,
+    },
+    PlanFound {
+        goal: usize,
+        nb_expanded_nodes: usize,
+        plan: Vec<PlannedAction>,
+    },
+    PlanNotFound {
+        goal: usize,
+        nb_expanded_nodes: usize,
+    },
+    ActionPerformed {
+        action: usize,
+        description: String,
+        success: bool,
+    },
+    TaskDecomposed {
+        task: usize,
+        method: usize,
+    },
+    NoApplicableMethod {
+        task: usize,
+    },
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DecisionRecord {
+    pub tick: usize,
+    pub entity: EntityId,
+    #[serde(flatten)]
+    pub event: DecisionEvent,
+}
+
+impl DecisionRecord {
+    pub fn description(&self) -> String {
+        match &self.event {
+            DecisionEvent::GoalChosen { goal, utilities } => format!(
+                "{}: goal {goal} chosen (utility {:.1})",
+                self.tick,
+                utilities.get(*goal).copied().unwrap_or_default()
+            ),
+            DecisionEvent::GoalInterrupted { goal } => {
+                format!("{}: goal {goal} interrupted", self.tick)
+            }
+            DecisionEvent::PlanFound {
+                nb_expanded_nodes,
+                plan,
+                ..
+            } => format!(
+                "{}: plan of {} actions found ({nb_expanded_nodes} nodes)",
+                self.tick,
+                plan.len()
+            ),
+            DecisionEvent::PlanNotFound {
+                nb_expanded_nodes, ..
+            } => format!("{}: no plan found ({nb_expanded_nodes} nodes)", self.tick),
+            DecisionEvent::ActionPerformed {
+                description,
+                success,
+                ..
+            } => format!(
+                "{}: {description} {}",
+                self.tick,
+                if *success { "succeeded" } else { "failed" }
+            ),
+            DecisionEvent::TaskDecomposed { task, method } => {
+                format!("{}: task {task} decomposed with method {method}", self.tick)
+            }
+            DecisionEvent::NoApplicableMethod { task } => {
+                format!("{}: no method applicable for task {task}", self.tick)
+            }
+        }
+    }
+}
+
+/// Record of the decisions taken by each agent (goals chosen, plans computed, actions performed),
+/// to understand afterwards why an agent behaved the way it did.
+/// Only the most recent records of each agent are kept, and the records of dead agents are kept
+/// until the number of agents exceeds the cap: the least recently active agent is then evicted.
+pub struct DecisionTrace {
+    max_records_per_agent: usize,
+    max_agents: usize,
+    tick: usize,
+    records: HashMap<EntityId, VecDeque<DecisionRecord>>,
+}
+
+impl DecisionTrace {
+    pub fn new(max_records_per_agent: usize, max_agents: usize) -> Self {
+        Self {
+            max_records_per_agent,
+            max_agents,
+            tick: 0,
+            records: HashMap::new(),
+        }
+    }
+
+    pub fn next_tick(&mut self) {
+        self.tick += 1;
+    }
+
+    pub fn record(&mut self, entity: EntityId, event: DecisionEvent) {
+        if !self.records.contains_key(&entity) && self.records.len() >= self.max_agents {
+            self.evict_least_recent_agent();
+        }
+        let records = self.records.entry(entity).or_default();
+        records.push_back(DecisionRecord {
+            tick: self.tick,
+            entity,
+            event,
+        });
+        while records.len() > self.max_records_per_agent {
+            records.pop_front();
+        }
+    }
+
+    fn evict_least_recent_agent(&mut self) {
+        let least_recent = self
+            .records
+            .iter()
+            .min_by_key(|(entity, records)| (records.back().map(|r| r.tick), **entity))
+            .map(|(entity, _)| *entity);
+        if let Some(entity) = least_recent {
+            self.records.remove(&entity);
+        }
+    }
+
+    pub fn records(&self, entity: EntityId) -> Option<&VecDeque<DecisionRecord>> {
+        self.records.get(&entity)
+    }
+
+    /// Write all the records as JSON lines, sorted by tick then by entity
+    pub fn write_json_lines<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut records: Vec<&DecisionRecord> = self.records.values().flatten().collect();
+        records.sort_by_key(|r| (r.tick, r.entity));
+        for record in records {
+            serde_json::to_writer(&mut *writer, record)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn export(&self, file_name: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_name)?);
+        self.write_json_lines(&mut writer)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decision_trace::{DecisionEvent, DecisionTrace, PlannedAction};
+
+    #[test]
+    fn test_records_are_bounded() {
+        let mut trace = DecisionTrace::new(2, 10);
+        for action in 0..3 {
+            trace.record(
+                1,
+                DecisionEvent::ActionPerformed {
+                    action,
+                    description: String::new(),
+                    success: true,
+                },
+            );
+            trace.next_tick();
+        }
+
+        let records = trace.records(1).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].tick, 1);
+        assert_eq!(records[1].tick, 2);
+        assert!(trace.records(2).is_none());
+    }
+
+    #[test]
+    fn test_json_lines_export() {
+        let mut trace = DecisionTrace::new(10, 10);
+        trace.record(
+            2,
+            DecisionEvent::GoalChosen {
+                goal: 0,
+                utilities: vec![0.5],
+            },
+        );
+        trace.record(
+            1,
+            DecisionEvent::PlanFound {
+                goal: 0,
+                nb_expanded_nodes: 3,
+                plan: vec![PlannedAction {
+                    action: 1,
+                    description: String::from("eat plant"),
+                    cost: 1.0,
+                }],
+            },
+        );
+        trace.next_tick();
+        trace.record(
+            2,
+            DecisionEvent::PlanNotFound {
+                goal: 0,
+                nb_expanded_nodes: 7,
+            },
+        );
+
+        let mut output = Vec::new();
+        trace.write_json_lines(&mut output).unwrap();
+        let lines: Vec<String> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"tick":0,"entity":1,"event":"plan_found","goal":0,"nb_expanded_nodes":3,"plan":[{"action":1,"description":"eat plant","cost":1.0}]}"#,
+                r#"{"tick":0,"entity":2,"event":"goal_chosen","goal":0,"utilities":[0.5]}"#,
+                r#"{"tick":1,"entity":2,"event":"plan_not_found","goal":0,"nb_expanded_nodes":7}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_least_recent_agent_evicted() {
+        let mut trace = DecisionTrace::new(10, 2);
+        let event = DecisionEvent::PlanNotFound {
+            goal: 0,
+            nb_expanded_nodes: 0,
+        };
+        trace.record(1, event.clone());
+        trace.record(2, event.clone());
+        trace.next_tick();
+        // Agent 2 is still active, agent 1 is dead: its records are kept until a new agent comes
+        trace.record(2, event.clone());
+        assert!(trace.records(1).is_some());
+
+        trace.next_tick();
+        trace.record(3, event);
+        assert!(trace.records(1).is_none());
+        assert_eq!(trace.records(2).unwrap().len(), 2);
+        assert_eq!(trace.records(3).unwrap().len(), 1);
+    }
+}
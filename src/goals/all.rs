@@ -1,21 +1,27 @@
 use ordered_float::OrderedFloat;
 
 use crate::{
-    components::all::CreatureComponent,
-    configuration::Config,
+    configuration::{Activity, Config},
     ecs::{Ecs, EntityInfo},
+    goals::utility_curves::UtilityFunction,
     goap::{Condition, Goal, Operator, Symbol, Value},
+    shared_data::climate,
+    systems::utils,
 };
 
 pub struct ReplenishEnergyGoal {
-    max_energy: f32,
+    utility: UtilityFunction,
     preconditions: [Condition; 1],
 }
 impl ReplenishEnergyGoal {
-    pub fn new(config: &Config) -> Self {
+    pub fn new(config: &Config, species: usize) -> Self {
         let max_energy = config.creature.max_energy;
         Self {
-            max_energy,
+            utility: UtilityFunction::from_config(
+                &config.species[species].utility.replenish_energy,
+                config,
+                species,
+            ),
             preconditions: [Condition::new(
                 Symbol::Energy,
                 Operator::GreaterOrEqual,
@@ -29,7 +35,148 @@ impl Goal for ReplenishEnergyGoal {
         &self.preconditions
     }
     fn utility(&self, ecs: &Ecs, info: &EntityInfo) -> f32 {
-        let creature = ecs.component::<CreatureComponent>(info).unwrap();
-        f32::max(self.max_energy - creature.energy, 0.0)
+        self.utility.evaluate(ecs, info)
+    }
+    fn utility_function(&self) -> Option<&UtilityFunction> {
+        Some(&self.utility)
+    }
+}
+
+pub struct FleeGoal {
+    utility: UtilityFunction,
+    preconditions: [Condition; 1],
+}
+impl FleeGoal {
+    /// Return None if the species has no flee utility configured
+    pub fn new(config: &Config, species: usize) -> Option<Self> {
+        let utility_config = config.species[species].utility.flee.as_ref()?;
+        Some(Self {
+            utility: UtilityFunction::from_config(utility_config, config, species),
+            preconditions: [Condition::new(
+                Symbol::IsSafe,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+        })
+    }
+}
+impl Goal for FleeGoal {
+    fn conditions(&self) -> &[Condition] {
+        &self.preconditions
+    }
+    fn utility(&self, ecs: &Ecs, info: &EntityInfo) -> f32 {
+        self.utility.evaluate(ecs, info)
+    }
+    fn utility_function(&self) -> Option<&UtilityFunction> {
+        Some(&self.utility)
+    }
+}
+
+pub struct ReproduceGoal {
+    utility: UtilityFunction,
+    preconditions: [Condition; 1],
+}
+impl ReproduceGoal {
+    pub fn new(config: &Config, species: usize) -> Self {
+        Self {
+            utility: UtilityFunction::from_config(
+                &config.species[species].utility.reproduce,
+                config,
+                species,
+            ),
+            preconditions: [Condition::new(
+                Symbol::HasMated,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+        }
+    }
+}
+impl Goal for ReproduceGoal {
+    fn conditions(&self) -> &[Condition] {
+        &self.preconditions
+    }
+    fn utility(&self, ecs: &Ecs, info: &EntityInfo) -> f32 {
+        // Only adults reproduce
+        if !utils::can_reproduce(ecs, info) {
+            return 0.0;
+        }
+        self.utility.evaluate(ecs, info)
+    }
+    fn utility_function(&self) -> Option<&UtilityFunction> {
+        Some(&self.utility)
+    }
+}
+
+pub struct RestGoal {
+    utility: UtilityFunction,
+    preconditions: [Condition; 1],
+    activity: Activity,
+    off_hours_utility: f32,
+}
+impl RestGoal {
+    pub fn new(config: &Config, species: usize) -> Self {
+        Self {
+            utility: UtilityFunction::from_config(
+                &config.species[species].utility.rest,
+                config,
+                species,
+            ),
+            activity: config.species[species].activity,
+            off_hours_utility: config.day.off_hours_rest_utility,
+            preconditions: [Condition::new(
+                Symbol::IsRested,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+        }
+    }
+}
+impl Goal for RestGoal {
+    fn conditions(&self) -> &[Condition] {
+        &self.preconditions
+    }
+    fn utility(&self, ecs: &Ecs, info: &EntityInfo) -> f32 {
+        // Creatures prefer to rest outside of their activity hours
+        if climate::current().is_active(self.activity) {
+            self.utility.evaluate(ecs, info)
+        } else {
+            self.utility.evaluate(ecs, info) + self.off_hours_utility
+        }
+    }
+    fn utility_function(&self) -> Option<&UtilityFunction> {
+        Some(&self.utility)
+    }
+}
+
+pub struct DrinkGoal {
+    utility: UtilityFunction,
+    preconditions: [Condition; 1],
+}
+impl DrinkGoal {
+    pub fn new(config: &Config, species: usize) -> Self {
+        Self {
+            utility: UtilityFunction::from_config(
+                &config.species[species].utility.drink,
+                config,
+                species,
+            ),
+            preconditions: [Condition::new(
+                Symbol::IsHydrated,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+        }
+    }
+}
+impl Goal for DrinkGoal {
+    fn conditions(&self) -> &[Condition] {
+        &self.preconditions
+    }
+    fn utility(&self, ecs: &Ecs, info: &EntityInfo) -> f32 {
+        self.utility.evaluate(ecs, info)
+    }
+    fn utility_function(&self) -> Option<&UtilityFunction> {
+        Some(&self.utility)
     }
 }
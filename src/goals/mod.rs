@@ -1 +1,2 @@
 pub mod all;
+pub mod utility_curves;
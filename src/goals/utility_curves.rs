@@ -0,0 +1,230 @@
+use serde::Deserialize;
+
+use crate::{
+    components::{all::CreatureComponent, body_component::BodyComponent},
+    configuration::{Config, CreatureConfig, GoalUtilityConfig},
+    ecs::{Ecs, EntityInfo},
+    systems::utils,
+};
+
+/// Response curve, mapping an input in [0; 1] to a score in [0; 1]
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UtilityCurve {
+    Linear { slope: f32, intercept: f32 },
+    Quadratic { slope: f32, intercept: f32 },
+    // Soft threshold around the midpoint, sharper as the steepness increases
+    Logistic { steepness: f32, midpoint: f32 },
+    Step { threshold: f32, low: f32, high: f32 },
+}
+
+impl UtilityCurve {
+    pub fn score(&self, x: f32) -> f32 {
+        let y = match *self {
+            UtilityCurve::Linear { slope, intercept } => slope * x + intercept,
+            UtilityCurve::Quadratic { slope, intercept } => slope * x * x + intercept,
+            UtilityCurve::Logistic {
+                steepness,
+                midpoint,
+            } => 1.0 / (1.0 + (-steepness * (x - midpoint)).exp()),
+            UtilityCurve::Step {
+                threshold,
+                low,
+                high,
+            } => {
+                if x < threshold {
+                    low
+                } else {
+                    high
+                }
+            }
+        };
+        y.clamp(0.0, 1.0)
+    }
+}
+
+/// Value read from the components of an agent, normalized in [0; 1]
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UtilityInput {
+    Energy,
+    EnergyDeficit,
+    Health,
+    HealthDeficit,
+    Fatigue,
+    Thirst,
+    // 1 when a predator is in contact, 0 when none is known within the perception distance
+    ThreatProximity,
+}
+
+impl UtilityInput {
+    pub fn name(&self) -> &'static str {
+        match self {
+            UtilityInput::Energy => "energy",
+            UtilityInput::EnergyDeficit => "energy deficit",
+            UtilityInput::Health => "health",
+            UtilityInput::HealthDeficit => "health deficit",
+            UtilityInput::Fatigue => "fatigue",
+            UtilityInput::Thirst => "thirst",
+            UtilityInput::ThreatProximity => "threat proximity",
+        }
+    }
+
+    fn value(
+        &self,
+        ecs: &Ecs,
+        info: &EntityInfo,
+        config: &CreatureConfig,
+        predators: &[usize],
+    ) -> Option<f32> {
+        let creature = || ecs.component::<CreatureComponent>(info);
+        let value = match self {
+            UtilityInput::Energy => creature()?.energy / config.max_energy,
+            UtilityInput::EnergyDeficit => 1.0 - creature()?.energy / config.max_energy,
+            UtilityInput::Health => creature()?.health / config.max_health,
+            UtilityInput::HealthDeficit => 1.0 - creature()?.health / config.max_health,
+            UtilityInput::Fatigue => creature()?.fatigue / config.max_fatigue,
+            UtilityInput::Thirst => creature()?.thirst / config.max_thirst,
+            UtilityInput::ThreatProximity => {
+                let body = ecs.component::<BodyComponent>(info)?;
+                let detection_distance = utils::perception_distance(ecs, info, config);
+                let threats = utils::known_threats(ecs, info, body, predators, config);
+                threats.first().map_or(0.0, |(distance_squared, _)| {
+                    1.0 - (distance_squared.sqrt() / detection_distance) as f32
+                })
+            }
+        };
+        Some(value.clamp(0.0, 1.0))
+    }
+}
+
+/// Input of the agent passed through a response curve
+pub struct Consideration {
+    input: UtilityInput,
+    curve: UtilityCurve,
+    creature_config: CreatureConfig,
+    // Species hunting the agent's species
+    predators: Vec<usize>,
+}
+
+impl Consideration {
+    pub fn new(input: UtilityInput, curve: UtilityCurve, config: &Config, species: usize) -> Self {
+        Self {
+            input,
+            curve,
+            creature_config: config.creature,
+            predators: config.predators_of(species),
+        }
+    }
+
+    pub fn input(&self) -> UtilityInput {
+        self.input
+    }
+
+    pub fn curve(&self) -> UtilityCurve {
+        self.curve
+    }
+
+    /// Current value of the input for the agent, if it has the required components
+    pub fn input_value(&self, ecs: &Ecs, info: &EntityInfo) -> Option<f32> {
+        self.input
+            .value(ecs, info, &self.creature_config, &self.predators)
+    }
+
+    pub fn score(&self, ecs: &Ecs, info: &EntityInfo) -> f32 {
+        self.input_value(ecs, info)
+            .map_or(0.0, |x| self.curve.score(x))
+    }
+}
+
+/// Utility of a goal: product of the scores of its considerations, scaled by a weight.
+/// The product lets any consideration veto the goal (a score of zero).
+pub struct UtilityFunction {
+    weight: f32,
+    considerations: Vec<Consideration>,
+}
+
+impl UtilityFunction {
+    pub fn new(weight: f32) -> Self {
+        Self {
+            weight,
+            considerations: Vec::new(),
+        }
+    }
+
+    pub fn from_config(
+        utility_config: &GoalUtilityConfig,
+        config: &Config,
+        species: usize,
+    ) -> Self {
+        let mut utility = Self::new(utility_config.weight);
+        utility.add(Consideration::new(
+            utility_config.input,
+            utility_config.curve,
+            config,
+            species,
+        ));
+        utility
+    }
+
+    pub fn add(&mut self, consideration: Consideration) {
+        self.considerations.push(consideration);
+    }
+
+    pub fn considerations(&self) -> &[Consideration] {
+        &self.considerations
+    }
+
+    pub fn evaluate(&self, ecs: &Ecs, info: &EntityInfo) -> f32 {
+        self.weight
+            * self
+                .considerations
+                .iter()
+                .map(|c| c.score(ecs, info))
+                .product::<f32>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::goals::utility_curves::UtilityCurve;
+
+    #[test]
+    fn test_curves() {
+        let linear = UtilityCurve::Linear {
+            slope: 1.0,
+            intercept: 0.0,
+        };
+        assert_eq!(linear.score(0.25), 0.25);
+
+        let quadratic = UtilityCurve::Quadratic {
+            slope: 1.0,
+            intercept: 0.0,
+        };
+        assert_eq!(quadratic.score(0.5), 0.25);
+
+        let logistic = UtilityCurve::Logistic {
+            steepness: 10.0,
+            midpoint: 0.5,
+        };
+        assert_eq!(logistic.score(0.5), 0.5);
+        assert!(logistic.score(0.0) < 0.01);
+        assert!(logistic.score(1.0) > 0.99);
+
+        let step = UtilityCurve::Step {
+            threshold: 0.5,
+            low: 0.1,
+            high: 0.9,
+        };
+        assert_eq!(step.score(0.4), 0.1);
+        assert_eq!(step.score(0.5), 0.9);
+
+        // Scores are clamped in [0; 1]
+        let steep = UtilityCurve::Linear {
+            slope: 4.0,
+            intercept: -1.0,
+        };
+        assert_eq!(steep.score(0.0), 0.0);
+        assert_eq!(steep.score(1.0), 1.0);
+    }
+}
@@ -8,15 +8,33 @@ use ordered_float::OrderedFloat;
 use crate::{
     components::agent_component::AgentComponent,
     configuration::Config,
-    ecs::{Ecs, EntityInfo},
+    decision_trace::{DecisionEvent, DecisionRecord, DecisionTrace, PlannedAction},
+    ecs::{Ecs, EntityId, EntityInfo},
+    goals::utility_curves::UtilityFunction,
+    htn::{Agenda, CompoundTask, Task},
 };
 
+// Maximum number of compound tasks decomposed to find the next primitive task of an agenda
+// (guards against recursive tasks)
+const MAX_DECOMPOSITIONS: usize = 100;
+
+// Minimal distance to an unsatisfied condition, for the planner heuristic
+const MIN_CONDITION_DISTANCE: f64 = 0.01;
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Symbol {
     Energy,
     IsNearPlant,
     IsNearCorpse,
-    IsNearHerbivorous,
+    IsNearPrey,
+    HasKilledPrey,
+    IsSafe,
+    IsRested,
+    IsHydrated,
+    IsNearWater,
+    IsNearMate,
+    HasMated,
+    HasReproductionEnergy,
 
     #[cfg(test)]
     HasHouse,
@@ -28,6 +46,10 @@ pub enum Symbol {
     WoodCount,
     #[cfg(test)]
     MoneyCount,
+    #[cfg(test)]
+    Activity,
+    #[cfg(test)]
+    Partner,
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
@@ -36,11 +58,40 @@ pub enum Value {
     #[allow(unused)]
     Isize(isize),
     Bool(bool),
+    // Reference to an entity (i.e. the target of an action)
+    #[allow(unused)]
+    Entity(EntityId),
+    // Symbolic value, for enum-like facts (i.e. the current activity of an agent)
+    #[allow(unused)]
+    Enum(&'static str),
+}
+
+impl Value {
+    // Numeric values can be ordered and measured, other values can only be tested for equality
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::F32(v) => Some(v.into_inner() as f64),
+            Value::Isize(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    // Numeric value equal to zero and of the same type, used as a default value
+    fn zero_like(&self) -> Option<Value> {
+        match self {
+            Value::F32(_) => Some(Value::F32(OrderedFloat(0.0))),
+            Value::Isize(_) => Some(Value::Isize(0)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum Operator {
     Equal,
+    // Not equal
+    #[allow(unused)]
+    Not,
     #[allow(unused)]
     Less,
     #[allow(unused)]
@@ -48,14 +99,18 @@ pub enum Operator {
     #[allow(unused)]
     Greater,
     GreaterOrEqual,
-    #[allow(unused)]
-    Not,
 }
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum Modifier {
     SetValue,
     Increment,
+    Decrement,
+    #[allow(unused)]
+    Multiply,
+    // Clamp the fact between the effect value (lower bound) and the given value (upper bound),
+    // see Effect::clamp
+    ClampToRange(Value),
 }
 
 #[derive(Clone, Eq, PartialEq, Hash)]
@@ -101,6 +156,11 @@ impl Effect {
             value,
         }
     }
+
+    /// Effect keeping the fact between the given bounds
+    pub fn clamp(symbol: Symbol, min: Value, max: Value) -> Self {
+        Effect::new(symbol, Modifier::ClampToRange(max), min)
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Hash)]
@@ -112,6 +172,85 @@ impl WorldState {
     pub fn new() -> Self {
         Self { facts: Vec::new() }
     }
+
+    /// Set the value of a fact, adding it if it is not in the world state yet
+    pub fn set(&mut self, symbol: Symbol, value: Value) {
+        if let Some(fact) = self.facts.iter_mut().find(|f| f.symbol == symbol) {
+            fact.value = value;
+        } else {
+            self.facts.push(Fact { symbol, value });
+        }
+    }
+}
+
+/// Maximum number of nodes expanded by the planner for a single plan, over all the ticks the
+/// search is spread on
+pub const MAX_EXPANDED_NODES: usize = 100_000;
+
+pub enum PlanningResult {
+    Found(Vec<usize>),
+    NotFound,
+    // The planning budget was exhausted before the end of the search, which can be resumed later
+    Pending,
+}
+
+/// State of an A* search on world states.
+/// It is stored on the agent, so that a search interrupted by the planning budget can be resumed
+/// on the next tick.
+#[derive(Clone)]
+pub struct PlannerState {
+    goal: usize,
+    // Used to decide which agents plan first when the planning budget is limited
+    priority: f32,
+    nb_ticks_waited: usize,
+    nb_expanded_nodes: usize,
+
+    // Set of discovered nodes
+    open_list: Vec<(WorldState, OrderedFloat<f64>)>,
+    came_from: HashMap<WorldState, (WorldState, usize)>,
+    g_cost: HashMap<WorldState, f64>,
+}
+
+impl PlannerState {
+    pub fn new(
+        start_state: &WorldState,
+        goal_conditions: &[Condition],
+        goal: usize,
+        priority: f32,
+    ) -> Self {
+        Self {
+            goal,
+            priority,
+            nb_ticks_waited: 0,
+            nb_expanded_nodes: 0,
+            open_list: vec![(
+                start_state.clone(),
+                OrderedFloat(distance(start_state, goal_conditions)),
+            )],
+            came_from: HashMap::new(),
+            g_cost: HashMap::from([(start_state.clone(), 0.0)]),
+        }
+    }
+
+    pub fn goal(&self) -> usize {
+        self.goal
+    }
+
+    pub fn priority(&self) -> f32 {
+        self.priority
+    }
+
+    pub fn nb_ticks_waited(&self) -> usize {
+        self.nb_ticks_waited
+    }
+
+    pub fn wait(&mut self) {
+        self.nb_ticks_waited += 1;
+    }
+
+    pub fn nb_expanded_nodes(&self) -> usize {
+        self.nb_expanded_nodes
+    }
 }
 
 pub enum ActionResult {
@@ -137,22 +276,57 @@ pub trait Action {
     ) -> Result<ActionResult, String>;
 
     fn description(&self) -> String;
+
+    // Multiplies the cost learnt by the agent, to favour some actions over others
+    fn cost_factor(&self) -> f64 {
+        1.0
+    }
 }
 
 pub trait Goal {
     fn conditions(&self) -> &[Condition];
     fn utility(&self, ecs: &Ecs, info: &EntityInfo) -> f32;
+
+    // Utility function the utility is computed with, if any (for debugging purposes)
+    fn utility_function(&self) -> Option<&UtilityFunction> {
+        None
+    }
+}
+
+/// Observation of the actual state of the world by an agent, to update its world state before
+/// choosing a goal
+pub trait Sensor {
+    fn sense(&self, ecs: &Ecs, info: &EntityInfo, config: &Config) -> (Symbol, Value);
 }
 
 pub struct GoalSet {
     goals: Vec<Box<dyn Goal>>,
+    // Sensors observing the facts the goals depend on
+    sensors: Vec<Box<dyn Sensor>>,
+    // Compound task pursued when the goal is chosen, instead of planning the goal directly
+    tasks: Vec<Option<usize>>,
 }
 impl GoalSet {
     pub fn new() -> Self {
-        GoalSet { goals: Vec::new() }
+        GoalSet {
+            goals: Vec::new(),
+            sensors: Vec::new(),
+            tasks: Vec::new(),
+        }
     }
     pub fn add(&mut self, goal: Box<dyn Goal>) {
         self.goals.push(goal);
+        self.tasks.push(None);
+    }
+    pub fn add_sensor(&mut self, sensor: Box<dyn Sensor>) {
+        self.sensors.push(sensor);
+    }
+    pub fn add_with_task(&mut self, goal: Box<dyn Goal>, compound_task: usize) {
+        self.goals.push(goal);
+        self.tasks.push(Some(compound_task));
+    }
+    pub fn len(&self) -> usize {
+        self.goals.len()
     }
 }
 
@@ -178,6 +352,9 @@ impl ActionSet {
 pub struct Goap {
     goal_sets: Vec<GoalSet>,
     action_sets: Vec<ActionSet>,
+    compound_tasks: Vec<CompoundTask>,
+    // Optional record of the decisions of each agent, for debugging purposes
+    decision_trace: Option<DecisionTrace>,
 }
 
 macro_rules! goal_exists_or_return {
@@ -223,6 +400,37 @@ impl Goap {
         Goap {
             goal_sets: Vec::new(),
             action_sets: Vec::new(),
+            compound_tasks: Vec::new(),
+            decision_trace: None,
+        }
+    }
+
+    pub fn enable_decision_trace(&mut self, max_records_per_agent: usize, max_agents: usize) {
+        self.decision_trace = Some(DecisionTrace::new(max_records_per_agent, max_agents));
+    }
+
+    pub fn decision_trace(&self) -> Option<&DecisionTrace> {
+        self.decision_trace.as_ref()
+    }
+
+    /// Return the most recent decisions of the agent, if the decision trace is enabled
+    pub fn agent_decisions(&self, entity: EntityId) -> impl Iterator<Item = &DecisionRecord> {
+        self.decision_trace
+            .as_ref()
+            .and_then(|trace| trace.records(entity))
+            .into_iter()
+            .flatten()
+    }
+
+    pub fn next_tick(&mut self) {
+        if let Some(trace) = &mut self.decision_trace {
+            trace.next_tick();
+        }
+    }
+
+    fn record(&mut self, entity: EntityId, event: impl FnOnce() -> DecisionEvent) {
+        if let Some(trace) = &mut self.decision_trace {
+            trace.record(entity, event());
         }
     }
 
@@ -236,26 +444,221 @@ impl Goap {
         self.action_sets.len() - 1
     }
 
-    pub fn find_goal(&self, ecs: &Ecs, info: &EntityInfo, goal_set: usize) -> Option<usize> {
+    pub fn add_compound_task(&mut self, compound_task: CompoundTask) -> usize {
+        self.compound_tasks.push(compound_task);
+        self.compound_tasks.len() - 1
+    }
+
+    /// Return the compound task to pursue for the goal, if the goal is not planned directly
+    pub fn goal_task(&self, goal_set: usize, goal: usize) -> Option<usize> {
+        goal_exists_or_return!(self, goal_set, goal, None);
+        self.goal_sets[goal_set].tasks[goal]
+    }
+
+    /// Pop the next primitive task (goal or action) of the agenda, decomposing the compound tasks
+    /// met on the way with their first method applicable on the world state.
+    /// If a compound task cannot be decomposed, the whole agenda is abandoned.
+    pub fn next_task(
+        &mut self,
+        entity: EntityId,
+        agenda: &mut Agenda,
+        world_state: &WorldState,
+    ) -> Option<Task> {
+        let mut nb_decompositions = 0;
+        while let Some(task) = agenda.pop() {
+            let Task::Compound(compound_task) = task else {
+                return Some(task);
+            };
+            if compound_task >= self.compound_tasks.len() {
+                error!("No compound task with index {compound_task}");
+                agenda.clear();
+                return None;
+            }
+            if nb_decompositions >= MAX_DECOMPOSITIONS {
+                error!(
+                    "Compound task {compound_task} not decomposed after {nb_decompositions} decompositions"
+                );
+                agenda.clear();
+                return None;
+            }
+            nb_decompositions += 1;
+
+            let Some(method) = self.compound_tasks[compound_task].find_method(world_state) else {
+                self.record(entity, || DecisionEvent::NoApplicableMethod {
+                    task: compound_task,
+                });
+                agenda.clear();
+                return None;
+            };
+            agenda.push_subtasks(self.compound_tasks[compound_task].method(method).subtasks());
+            self.record(entity, || DecisionEvent::TaskDecomposed {
+                task: compound_task,
+                method,
+            });
+        }
+        None
+    }
+
+    pub fn get_task_description(&self, action_set: usize, task: &Task) -> String {
+        match task {
+            Task::Goal(goal) => format!("goal {goal}"),
+            Task::Action(action) => self
+                .get_description(action_set, *action)
+                .unwrap_or_else(|| format!("unknown action {action}")),
+            Task::Compound(compound_task) => self
+                .compound_tasks
+                .get(*compound_task)
+                .map(|t| t.name().to_string())
+                .unwrap_or_else(|| format!("unknown task {compound_task}")),
+        }
+    }
+
+    /// Update the world state with the observations of the sensors of the goal set
+    pub fn sense(
+        &self,
+        ecs: &Ecs,
+        info: &EntityInfo,
+        config: &Config,
+        goal_set: usize,
+        world_state: &mut WorldState,
+    ) {
         if goal_set >= self.goal_sets.len() {
-            error!("No goal set with index {}", goal_set);
-            return None;
+            error!("No goal set with index {goal_set}");
+            return;
         }
+        for sensor in self.goal_sets[goal_set].sensors.iter() {
+            let (symbol, value) = sensor.sense(ecs, info, config);
+            world_state.set(symbol, value);
+        }
+    }
+
+    /// Check if another goal has become more useful than the current one, by more than the margin
+    pub fn should_interrupt(
+        &self,
+        ecs: &Ecs,
+        info: &EntityInfo,
+        goal_set: usize,
+        goal: usize,
+        margin: f32,
+    ) -> bool {
+        goal_exists_or_return!(self, goal_set, goal, false);
+        let goals = &self.goal_sets[goal_set].goals;
+        let current_utility = goals[goal].utility(ecs, info);
+        goals
+            .iter()
+            .enumerate()
+            .any(|(idx, g)| idx != goal && g.utility(ecs, info) > current_utility + margin)
+    }
+
+    pub fn record_interruption(&mut self, entity: EntityId, goal: usize) {
+        self.record(entity, || DecisionEvent::GoalInterrupted { goal });
+    }
+
+    /// Return the goal with the best utility, along with this utility.
+    /// Goals already satisfied in the world state are not worth pursuing and are ignored.
+    pub fn find_goal(
+        &mut self,
+        ecs: &Ecs,
+        info: &EntityInfo,
+        goal_set: usize,
+        world_state: &WorldState,
+    ) -> Option<(usize, f32)> {
+        goal_set_exists_or_return!(self, goal_set, None);
+
+        let utilities: Vec<f32> = self.goal_sets[goal_set]
+            .goals
+            .iter()
+            .map(|goal| goal.utility(ecs, info))
+            .collect();
 
         let mut found = false;
         let mut best_goal_idx = 0;
         let mut best_utility = 0.0;
-        for (idx, goal) in self.goal_sets[goal_set].goals.iter().enumerate() {
-            let utility = goal.utility(ecs, info);
-            if utility > best_utility {
+        let goals = &self.goal_sets[goal_set].goals;
+        for (idx, utility) in utilities.iter().enumerate() {
+            if *utility > best_utility && !validate_conditions(world_state, goals[idx].conditions())
+            {
                 found = true;
                 best_goal_idx = idx;
-                best_utility = utility;
+                best_utility = *utility;
+            }
+        }
+        if !found {
+            return None;
+        }
+
+        self.record(info.entity, || DecisionEvent::GoalChosen {
+            goal: best_goal_idx,
+            utilities,
+        });
+        Some((best_goal_idx, best_utility))
+    }
+
+    pub fn start_plan(
+        &self,
+        world_state: &WorldState,
+        goal: usize,
+        goal_set: usize,
+        priority: f32,
+    ) -> Option<PlannerState> {
+        goal_exists_or_return!(self, goal_set, goal, None);
+        let goal_conditions = self.goal_sets[goal_set].goals[goal].conditions();
+        Some(PlannerState::new(
+            world_state,
+            goal_conditions,
+            goal,
+            priority,
+        ))
+    }
+
+    /// Resume the search of a plan, expanding at most `budget` nodes.
+    /// Return the result of the search and the number of nodes expanded.
+    pub fn continue_plan(
+        &mut self,
+        entity: EntityId,
+        agent: &AgentComponent,
+        planner: &mut PlannerState,
+        goal_set: usize,
+        action_set: usize,
+        budget: usize,
+    ) -> (PlanningResult, usize) {
+        let goal = planner.goal;
+        goal_exists_or_return!(self, goal_set, goal, (PlanningResult::NotFound, 0));
+        action_set_exists_or_return!(self, action_set, (PlanningResult::NotFound, 0));
+        let goal_conditions = self.goal_sets[goal_set].goals[goal].conditions();
+        let actions = &self.action_sets[action_set].actions;
+        let (result, nb_expanded_nodes) =
+            resume_path_search(planner, agent, goal_conditions, actions, budget);
+
+        let nb_expanded_nodes_total = planner.nb_expanded_nodes;
+        match &result {
+            PlanningResult::Found(plan) => {
+                let plan = plan
+                    .iter()
+                    .map(|action| PlannedAction {
+                        action: *action,
+                        description: actions[*action].description(),
+                        cost: agent.get_action_cost(*action) * actions[*action].cost_factor(),
+                    })
+                    .collect();
+                self.record(entity, || DecisionEvent::PlanFound {
+                    goal,
+                    nb_expanded_nodes: nb_expanded_nodes_total,
+                    plan,
+                });
             }
+            PlanningResult::NotFound => {
+                self.record(entity, || DecisionEvent::PlanNotFound {
+                    goal,
+                    nb_expanded_nodes: nb_expanded_nodes_total,
+                });
+            }
+            PlanningResult::Pending => {}
         }
-        if found { Some(best_goal_idx) } else { None }
+        (result, nb_expanded_nodes)
     }
 
+    #[cfg(test)]
     pub fn compute_plan(
         &self,
         agent: &AgentComponent,
@@ -276,7 +679,7 @@ impl Goap {
     }
 
     pub fn perform_action(
-        &self,
+        &mut self,
         ecs: &mut Ecs,
         info: &EntityInfo,
         config: &Config,
@@ -286,9 +689,9 @@ impl Goap {
     ) -> ActionResult {
         action_exists_or_return!(self, action_set, action, ActionResult::Failure);
         let act = &self.action_sets[action_set].actions[action];
-        match act.perform(ecs, info, config) {
+        let result = match act.perform(ecs, info, config) {
             Ok(ActionResult::Success) => {
-                apply_effects(act.as_ref(), world_state);
+                apply_effects(act.effects(), world_state);
                 ActionResult::Success
             }
             Ok(result) => result,
@@ -296,7 +699,30 @@ impl Goap {
                 error!("{msg}");
                 ActionResult::Failure
             }
-        }
+        };
+
+        let success = match result {
+            ActionResult::Success => true,
+            ActionResult::Failure => false,
+            ActionResult::OnGoing => return result,
+        };
+        let description = act.description();
+        self.record(info.entity, || DecisionEvent::ActionPerformed {
+            action,
+            description,
+            success,
+        });
+        result
+    }
+
+    /// Return the utility functions of the goals of the goal set
+    pub fn utility_functions(&self, goal_set: usize) -> Vec<&UtilityFunction> {
+        goal_set_exists_or_return!(self, goal_set, Vec::new());
+        self.goal_sets[goal_set]
+            .goals
+            .iter()
+            .filter_map(|goal| goal.utility_function())
+            .collect()
     }
 
     pub fn get_description(&self, action_set: usize, action: usize) -> Option<String> {
@@ -312,26 +738,45 @@ impl Goap {
 }
 
 /// Apply the A* algorithm to find a path of actions from the initial world state to the goal world state
-///
-/// # Note about optimisation
-/// See note in path_finding (similar A* implementation)
+#[cfg(test)]
 pub fn find_path(
     agent: &AgentComponent,
     start_state: &WorldState,
     goal_conditions: &[Condition],
     action_set: &[Box<dyn Action>],
 ) -> Option<Vec<usize>> {
-    // Set of discovered nodes
-    let mut open_list: Vec<(WorldState, OrderedFloat<f64>)> = Vec::new();
-    open_list.push((
-        start_state.clone(),
-        OrderedFloat(distance(start_state, goal_conditions)),
-    ));
-
-    let mut came_from: HashMap<WorldState, (WorldState, usize)> = HashMap::new();
+    let mut planner = PlannerState::new(start_state, goal_conditions, 0, 0.0);
+    match resume_path_search(
+        &mut planner,
+        agent,
+        goal_conditions,
+        action_set,
+        MAX_EXPANDED_NODES,
+    ) {
+        (PlanningResult::Found(plan), _) => Some(plan),
+        _ => None,
+    }
+}
 
-    let mut g_cost: HashMap<WorldState, f64> = HashMap::new();
-    g_cost.insert(start_state.clone(), 0.0);
+/// Continue the A* search stored in the planner state, expanding at most `budget` nodes.
+/// Return the result of the search and the number of nodes expanded during this call.
+///
+/// # Note about optimisation
+/// See note in path_finding (similar A* implementation)
+pub fn resume_path_search(
+    planner: &mut PlannerState,
+    agent: &AgentComponent,
+    goal_conditions: &[Condition],
+    action_set: &[Box<dyn Action>],
+    budget: usize,
+) -> (PlanningResult, usize) {
+    let PlannerState {
+        open_list,
+        came_from,
+        g_cost,
+        nb_expanded_nodes,
+        ..
+    } = planner;
 
     // TODO limit the search depth more properly
     // Currently I search from start to goal, and I try all available actions.
@@ -340,8 +785,15 @@ pub fn find_path(
     // Maybe searching from goal to start with a reversed logic would help ? (but then using
     // the ecs to compute the action cost becomes hard)
     let mut i = 0;
-    let i_max = 100_000;
-    while !open_list.is_empty() && i < i_max {
+    while !open_list.is_empty() {
+        if *nb_expanded_nodes >= MAX_EXPANDED_NODES {
+            error!("No action plan found after {nb_expanded_nodes} iterations");
+            return (PlanningResult::NotFound, i);
+        }
+        if i >= budget {
+            return (PlanningResult::Pending, i);
+        }
+
         // Get the node with the best score (estimated distance to goal with the current path)
         let (u_index, u): (usize, WorldState) = open_list
             .iter()
@@ -353,7 +805,7 @@ pub fn find_path(
 
         // Goal reached, return this path (the best one found yet)
         if validate_conditions(&u, goal_conditions) {
-            return Some(reconstruct_path(&came_from, u));
+            return (PlanningResult::Found(reconstruct_path(came_from, u)), i);
         }
 
         for (action_index, action) in action_set
@@ -363,11 +815,11 @@ pub fn find_path(
         {
             // Agents maintains a custom cost for each action (increased when performing an action
             // fails, to allow alternative plans to emerge)
-            let action_cost = agent.get_action_cost(action_index);
+            let action_cost = agent.get_action_cost(action_index) * action.cost_factor();
 
             // Create the neighbour v (world state obtained when performing the action)
             let mut v = u.clone();
-            apply_effects(action.as_ref(), &mut v);
+            apply_effects(action.effects(), &mut v);
 
             // Check if this path is better than any previous one that passes through v.
             // To do this, compute the length of the path from start to v.
@@ -389,83 +841,87 @@ pub fn find_path(
         }
 
         i += 1;
-    }
-    if i >= i_max {
-        error!("No action plan found after {i} iterations");
+        *nb_expanded_nodes += 1;
     }
 
-    None
+    (PlanningResult::NotFound, i)
 }
 
+/// Estimate the distance between the world state and the satisfaction of the conditions.
+/// Each unsatisfied condition counts for at most 1 (the base cost of an action). Numeric
+/// conditions count proportionally to the gap with their threshold, so that the planner favours
+/// states getting closer to satisfying them.
 fn distance(state: &WorldState, conditions: &[Condition]) -> f64 {
-    let mut d = 0.0;
-    for c in conditions {
-        if !validates_condition(state, c) {
-            d += 1.0;
-        }
-    }
-    d
+    conditions
+        .iter()
+        .map(|c| condition_distance(state, c))
+        .sum()
 }
 
-fn validates_condition(world_state: &WorldState, condition: &Condition) -> bool {
-    // Remark: We assume that there is only one entry per symbol in the world state.
+fn condition_distance(state: &WorldState, condition: &Condition) -> f64 {
+    if validates_condition(state, condition) {
+        return 0.0;
+    }
 
-    for Fact { symbol, value } in &world_state.facts {
-        if *symbol != condition.symbol {
-            continue;
+    let is_ordering = matches!(
+        condition.operator,
+        Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual
+    );
+    match find_value(state, &condition.symbol)
+        .and_then(|v| v.as_f64())
+        .zip(condition.value.as_f64())
+    {
+        Some((f_v, c_v)) if is_ordering => {
+            let scale = f64::max(1.0, f64::max(f_v.abs(), c_v.abs()));
+            ((c_v - f_v).abs() / scale).clamp(MIN_CONDITION_DISTANCE, 1.0)
         }
+        _ => 1.0,
+    }
+}
 
-        // Symbol found in the world state: check the condition
-        match (&condition.operator, &condition.value, value) {
-            (Operator::Equal, c_v, f_v) => {
-                return *f_v == *c_v;
-            }
-            (Operator::Not, c_v, f_v) => {
-                return *f_v != *c_v;
-            }
-            (Operator::Less, Value::F32(c_v), Value::F32(f_v)) => {
-                return *f_v < *c_v;
-            }
-            (Operator::Less, Value::Isize(c_v), Value::Isize(f_v)) => {
-                return *f_v < *c_v;
-            }
-            (Operator::LessOrEqual, Value::F32(c_v), Value::F32(f_v)) => {
-                return *f_v <= *c_v;
-            }
-            (Operator::LessOrEqual, Value::Isize(c_v), Value::Isize(f_v)) => {
-                return *f_v <= *c_v;
-            }
-            (Operator::Greater, Value::F32(c_v), Value::F32(f_v)) => {
-                return *f_v > *c_v;
-            }
-            (Operator::Greater, Value::Isize(c_v), Value::Isize(f_v)) => {
-                return *f_v > *c_v;
-            }
-            (Operator::GreaterOrEqual, Value::F32(c_v), Value::F32(f_v)) => {
-                return *f_v >= *c_v;
-            }
-            (Operator::GreaterOrEqual, Value::Isize(c_v), Value::Isize(f_v)) => {
-                return *f_v >= *c_v;
-            }
-            _ => {}
-        }
+// Remark: We assume that there is only one entry per symbol in the world state.
+fn find_value<'s>(world_state: &'s WorldState, symbol: &Symbol) -> Option<&'s Value> {
+    world_state
+        .facts
+        .iter()
+        .find(|fact| fact.symbol == *symbol)
+        .map(|fact| &fact.value)
+}
 
-        // If we reach this point, it means that something went wrong when checking the condition
-        error!(
-            "Inconsistency for symbol {:?}
-            Fact:      value = {:?}
-            Condition: value = {:?}, operator = {:?}",
-            symbol, value, condition.value, condition.operator
-        );
+fn validates_condition(world_state: &WorldState, condition: &Condition) -> bool {
+    let Some(value) = find_value(world_state, &condition.symbol) else {
+        // No symbol matching the condition found in the world state
         return false;
+    };
+
+    // Symbol found in the world state: check the condition
+    let ordering = match (value, &condition.value) {
+        (Value::F32(f_v), Value::F32(c_v)) => Some(f_v.cmp(c_v)),
+        (Value::Isize(f_v), Value::Isize(c_v)) => Some(f_v.cmp(c_v)),
+        _ => None,
+    };
+    match (&condition.operator, ordering) {
+        (Operator::Equal, _) => return *value == condition.value,
+        (Operator::Not, _) => return *value != condition.value,
+        (Operator::Less, Some(o)) => return o.is_lt(),
+        (Operator::LessOrEqual, Some(o)) => return o.is_le(),
+        (Operator::Greater, Some(o)) => return o.is_gt(),
+        (Operator::GreaterOrEqual, Some(o)) => return o.is_ge(),
+        _ => {}
     }
 
-    // No symbol matching the condition found in the world state
+    // If we reach this point, it means that something went wrong when checking the condition
+    error!(
+        "Inconsistency for symbol {:?}
+        Fact:      value = {:?}
+        Condition: value = {:?}, operator = {:?}",
+        condition.symbol, value, condition.value, condition.operator
+    );
     false
 }
 
-fn validate_conditions(state: &WorldState, conditions: &[Condition]) -> bool {
-    distance(state, conditions) == 0.0
+pub fn validate_conditions(state: &WorldState, conditions: &[Condition]) -> bool {
+    conditions.iter().all(|c| validates_condition(state, c))
 }
 
 fn reconstruct_path(
@@ -482,87 +938,88 @@ fn reconstruct_path(
     path
 }
 
-fn apply_effects(action: &dyn Action, state: &mut WorldState) {
+fn apply_effects(effects: &[Effect], state: &mut WorldState) {
     for Effect {
         symbol,
         modifier,
         value,
-    } in action.effects()
+    } in effects
     {
         // Look for the symbol in the world state
-        let mut symbol_in_state = false;
-        for fact in state.facts.iter_mut() {
-            if fact.symbol != *symbol {
-                continue;
-            }
-
+        if let Some(fact) = state.facts.iter_mut().find(|f| f.symbol == *symbol) {
             // Symbol matches, edit the existing symbol in the world state
-            match (modifier, value, &mut fact.value) {
-                (Modifier::SetValue, e_v, f_v) => {
-                    if mem::discriminant(f_v) == mem::discriminant(e_v) {
-                        *f_v = e_v.clone();
-                        symbol_in_state = true;
-                        break;
-                    }
-                }
-                (Modifier::Increment, Value::F32(e_v), Value::F32(f_v)) => {
-                    fact.value = Value::F32(*f_v + e_v);
-                    symbol_in_state = true;
-                    break;
-                }
-                (Modifier::Increment, Value::Isize(e_v), Value::Isize(f_v)) => {
-                    fact.value = Value::Isize(*f_v + e_v);
-                    symbol_in_state = true;
-                    break;
-                }
-                _ => {}
-            };
-
-            // If we reach this point, it means that something went wrong when editing the already
-            // existing fact
-            error!(
-                "Inconsistency for symbol {:?}\n
-                Fact:   value = {:?}\n
-                Effect: value = {:?}, modifier = {:?}",
-                fact.symbol, fact.value, value, modifier
-            );
+            if let Some(v) = modified_value(modifier, value, &fact.value) {
+                fact.value = v;
+            } else {
+                // If we reach this point, it means that something went wrong when editing the
+                // already existing fact
+                error!(
+                    "Inconsistency for symbol {:?}\n
+                    Fact:   value = {:?}\n
+                    Effect: value = {:?}, modifier = {:?}",
+                    fact.symbol, fact.value, value, modifier
+                );
+            }
         }
-
         // If the symbol is not in the world state, create it.
-        // For increment / decrement operations, acts as if there was a zero value and increment /
-        // decrement it.
-        if !symbol_in_state {
-            match (modifier, value) {
-                (Modifier::SetValue, v) => state.facts.push(Fact {
-                    symbol: symbol.clone(),
-                    value: v.clone(),
-                }),
-                (Modifier::Increment, Value::F32(e_v)) => state.facts.push(Fact {
-                    symbol: symbol.clone(),
-                    value: Value::F32(*e_v),
-                }),
-                (Modifier::Increment, Value::Isize(e_v)) => state.facts.push(Fact {
-                    symbol: symbol.clone(),
-                    value: Value::Isize(*e_v),
-                }),
-                _ => {}
-            };
+        // For numeric operations, acts as if there was a zero value and modify it.
+        else if let Some(v) = match modifier {
+            Modifier::SetValue => Some(value.clone()),
+            _ => value
+                .zero_like()
+                .and_then(|zero| modified_value(modifier, value, &zero)),
+        } {
+            state.facts.push(Fact {
+                symbol: symbol.clone(),
+                value: v,
+            });
         }
     }
 }
 
+// Return the value of a fact modified by an effect, or None if the effect cannot apply to it
+fn modified_value(modifier: &Modifier, e_v: &Value, f_v: &Value) -> Option<Value> {
+    match (modifier, e_v, f_v) {
+        (Modifier::SetValue, e_v, f_v) if mem::discriminant(f_v) == mem::discriminant(e_v) => {
+            Some(e_v.clone())
+        }
+        (Modifier::Increment, Value::F32(e_v), Value::F32(f_v)) => Some(Value::F32(*f_v + e_v)),
+        (Modifier::Increment, Value::Isize(e_v), Value::Isize(f_v)) => {
+            Some(Value::Isize(f_v + e_v))
+        }
+        (Modifier::Decrement, Value::F32(e_v), Value::F32(f_v)) => Some(Value::F32(*f_v - e_v)),
+        (Modifier::Decrement, Value::Isize(e_v), Value::Isize(f_v)) => {
+            Some(Value::Isize(f_v - e_v))
+        }
+        (Modifier::Multiply, Value::F32(e_v), Value::F32(f_v)) => Some(Value::F32(*f_v * e_v)),
+        (Modifier::Multiply, Value::Isize(e_v), Value::Isize(f_v)) => Some(Value::Isize(f_v * e_v)),
+        (Modifier::ClampToRange(Value::F32(max)), Value::F32(min), Value::F32(f_v)) => {
+            Some(Value::F32((*f_v).max(*min).min(*max)))
+        }
+        (Modifier::ClampToRange(Value::Isize(max)), Value::Isize(min), Value::Isize(f_v)) => {
+            Some(Value::Isize((*f_v).max(*min).min(*max)))
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::any::type_name;
 
+    use ordered_float::OrderedFloat;
+
     use crate::{
         components::agent_component::AgentComponent,
         configuration::Config,
+        decision_trace::{DecisionEvent, DecisionRecord},
         ecs::{Ecs, EntityInfo},
         goap::{
             Action, ActionResult, ActionSet, Condition, Effect, Fact, Goal, GoalSet, Goap,
-            Modifier, Operator, Symbol, Value, WorldState,
+            MIN_CONDITION_DISTANCE, Modifier, Operator, PlanningResult, Symbol, Value, WorldState,
+            apply_effects, distance, find_value, validate_conditions, validates_condition,
         },
+        htn::{Agenda, CompoundTask, Task},
     };
     macro_rules! define_perform_success {
         () => {
@@ -763,14 +1220,33 @@ mod tests {
             .expect("No plan found");
         print_plan(&goap, 0, &plan);
 
+        // The previous expectation (plant, plant, wait, cut, wait, cut, build) is still a valid
+        // plan of the same cost (7 actions): only the tie between the 2 optimal plans is broken
+        // differently. The previous heuristic counted 1 for each unsatisfied condition, so cutting
+        // one of the 3 trees first (no longer satisfying TreeCount >= 3) was estimated as far as
+        // the start. The numeric distance only counts 1/3 for it, so cutting first is explored
+        // first, and reaches the goal first.
+        let previous_plan = [1, 1, 4, 2, 4, 2, 0];
+        assert_eq!(plan.len(), previous_plan.len());
+        let mut state = world_state.clone();
+        for action in previous_plan {
+            let action = goap.get_action(0, action).unwrap();
+            assert!(validate_conditions(&state, action.preconditions()));
+            apply_effects(action.effects(), &mut state);
+        }
+        assert!(validate_conditions(
+            &state,
+            HaveHouseAndGardenGoal::new().conditions()
+        ));
+
         let expected_plan = vec![
-            type_name::<PlantTreeAction>(),
-            type_name::<PlantTreeAction>(),
-            type_name::<WaitForOneTreeToGrowAction>(),
             type_name::<CutTreeAction>(),
-            type_name::<WaitForOneTreeToGrowAction>(),
             type_name::<CutTreeAction>(),
             type_name::<BuildWoodHouseAction>(),
+            type_name::<PlantTreeAction>(),
+            type_name::<WaitForOneTreeToGrowAction>(),
+            type_name::<PlantTreeAction>(),
+            type_name::<WaitForOneTreeToGrowAction>(),
         ];
         validate_plan(&goap, 0, &plan, expected_plan);
     }
@@ -794,6 +1270,36 @@ mod tests {
         validate_plan(&goap, 0, &plan, expected_plan);
     }
 
+    #[test]
+    fn test_decision_trace() {
+        let mock_agent = AgentComponent::new(0, 0, 0);
+        let mut goap = create_goap(/*patient*/ false);
+        goap.enable_decision_trace(10, 10);
+        let world_state =
+            create_world_state(/*house*/ false, /*trees*/ 3, /*money*/ 10);
+
+        let mut planner = goap.start_plan(&world_state, 0, 0, 0.0).unwrap();
+        goap.continue_plan(1, &mock_agent, &mut planner, 0, 0, 1000);
+
+        let records: Vec<&DecisionRecord> = goap.agent_decisions(1).collect();
+        assert_eq!(records.len(), 1);
+        let DecisionEvent::PlanFound {
+            goal,
+            nb_expanded_nodes,
+            plan,
+        } = &records[0].event
+        else {
+            panic!("Plan not recorded");
+        };
+        assert_eq!(*goal, 0);
+        assert_eq!(*nb_expanded_nodes, planner.nb_expanded_nodes());
+        assert_eq!(
+            plan.iter().map(|a| (a.action, a.cost)).collect::<Vec<_>>(),
+            vec![(3, 1.0), (0, 1.0)]
+        );
+        assert_eq!(goap.agent_decisions(2).count(), 0);
+    }
+
     #[test]
     fn test_plan_not_found() {
         let mock_agent = AgentComponent::new(0, 0, 0);
@@ -803,6 +1309,319 @@ mod tests {
         assert_eq!(goap.compute_plan(&mock_agent, &world_state, 0, 0, 0), None);
     }
 
+    #[test]
+    fn test_plan_found_over_several_ticks() {
+        let mock_agent = AgentComponent::new(0, 0, 0);
+        let mut goap = create_goap(/*patient*/ true);
+        let world_state =
+            create_world_state(/*house*/ false, /*trees*/ 3, /*money*/ 0);
+        let expected_plan = goap
+            .compute_plan(&mock_agent, &world_state, 0, 0, 0)
+            .expect("No plan found");
+
+        // Expand a single node per tick, the search must resume where it stopped
+        let mut planner = goap.start_plan(&world_state, 0, 0, 0.0).unwrap();
+        let mut nb_ticks = 0;
+        let plan = loop {
+            nb_ticks += 1;
+            match goap.continue_plan(1, &mock_agent, &mut planner, 0, 0, 1) {
+                (PlanningResult::Pending, nb_expanded_nodes) => {
+                    assert_eq!(nb_expanded_nodes, 1);
+                }
+                (PlanningResult::Found(plan), _) => break plan,
+                (PlanningResult::NotFound, _) => panic!("No plan found"),
+            }
+        };
+
+        assert!(nb_ticks > 1);
+        assert_eq!(planner.nb_expanded_nodes(), nb_ticks - 1);
+        assert_eq!(plan, expected_plan);
+    }
+
+    #[test]
+    fn test_task_decomposition() {
+        let mut goap = create_goap(/*patient*/ false);
+        goap.enable_decision_trace(10, 10);
+
+        // Settle: build a house if there is none, then plant a tree
+        let mut settle = CompoundTask::new("settle");
+        settle.add_method(
+            vec![Condition::new(
+                Symbol::HasHouse,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+            vec![Task::Action(1)],
+        );
+        settle.add_method(vec![], vec![Task::Goal(0), Task::Action(1)]);
+        let settle = goap.add_compound_task(settle);
+
+        // Move: only possible with money, settle afterwards
+        let mut relocate = CompoundTask::new("relocate");
+        relocate.add_method(
+            vec![Condition::new(
+                Symbol::MoneyCount,
+                Operator::GreaterOrEqual,
+                Value::Isize(10),
+            )],
+            vec![Task::Action(3), Task::Compound(settle)],
+        );
+        let relocate = goap.add_compound_task(relocate);
+
+        let mut next_tasks = |agenda: &mut Agenda, world_state: &WorldState| {
+            let mut tasks = Vec::new();
+            while let Some(task) = goap.next_task(1, agenda, world_state) {
+                tasks.push(task);
+            }
+            tasks
+        };
+
+        let mut agenda = Agenda::new(Task::Compound(relocate), 0, 1.0);
+        let tasks = next_tasks(&mut agenda, &create_world_state(false, 0, 10));
+        assert_eq!(tasks, vec![Task::Action(3), Task::Goal(0), Task::Action(1)]);
+
+        let mut agenda = Agenda::new(Task::Compound(relocate), 0, 1.0);
+        let tasks = next_tasks(&mut agenda, &create_world_state(true, 0, 10));
+        assert_eq!(tasks, vec![Task::Action(3), Task::Action(1)]);
+
+        // No applicable method: the agenda is abandoned
+        let mut agenda = Agenda::new(Task::Compound(relocate), 0, 1.0);
+        let tasks = next_tasks(&mut agenda, &create_world_state(true, 0, 0));
+        assert!(tasks.is_empty());
+        assert!(agenda.is_empty());
+
+        let records: Vec<&DecisionEvent> = goap.agent_decisions(1).map(|r| &r.event).collect();
+        assert_eq!(
+            records.last(),
+            Some(&&DecisionEvent::NoApplicableMethod { task: relocate })
+        );
+        assert_eq!(
+            records[0],
+            &DecisionEvent::TaskDecomposed {
+                task: relocate,
+                method: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_operators() {
+        let state = WorldState {
+            facts: vec![
+                Fact::new(Symbol::WoodCount, Value::Isize(2)),
+                Fact::new(Symbol::Energy, Value::F32(OrderedFloat(10.0))),
+                Fact::new(Symbol::HasHouse, Value::Bool(false)),
+                Fact::new(Symbol::Activity, Value::Enum("sleeping")),
+            ],
+        };
+        let check = |symbol, operator, value| {
+            validates_condition(&state, &Condition::new(symbol, operator, value))
+        };
+        let f = |v| Value::F32(OrderedFloat(v));
+
+        assert!(check(Symbol::WoodCount, Operator::Equal, Value::Isize(2)));
+        assert!(!check(Symbol::WoodCount, Operator::Equal, Value::Isize(3)));
+        assert!(check(Symbol::WoodCount, Operator::Not, Value::Isize(3)));
+        assert!(!check(Symbol::WoodCount, Operator::Not, Value::Isize(2)));
+        assert!(check(Symbol::WoodCount, Operator::Less, Value::Isize(3)));
+        assert!(!check(Symbol::WoodCount, Operator::Less, Value::Isize(2)));
+        assert!(check(
+            Symbol::WoodCount,
+            Operator::LessOrEqual,
+            Value::Isize(2)
+        ));
+        assert!(!check(
+            Symbol::WoodCount,
+            Operator::LessOrEqual,
+            Value::Isize(1)
+        ));
+        assert!(check(Symbol::WoodCount, Operator::Greater, Value::Isize(1)));
+        assert!(!check(
+            Symbol::WoodCount,
+            Operator::Greater,
+            Value::Isize(2)
+        ));
+        assert!(check(
+            Symbol::WoodCount,
+            Operator::GreaterOrEqual,
+            Value::Isize(2)
+        ));
+        assert!(!check(
+            Symbol::WoodCount,
+            Operator::GreaterOrEqual,
+            Value::Isize(3)
+        ));
+
+        assert!(check(Symbol::Energy, Operator::Less, f(10.5)));
+        assert!(check(Symbol::Energy, Operator::LessOrEqual, f(10.0)));
+        assert!(check(Symbol::Energy, Operator::Greater, f(9.5)));
+        assert!(!check(Symbol::Energy, Operator::GreaterOrEqual, f(10.5)));
+
+        assert!(check(Symbol::HasHouse, Operator::Not, Value::Bool(true)));
+        assert!(check(
+            Symbol::Activity,
+            Operator::Equal,
+            Value::Enum("sleeping")
+        ));
+        assert!(check(
+            Symbol::Activity,
+            Operator::Not,
+            Value::Enum("eating")
+        ));
+
+        // Entities can only be tested for equality
+        let entity_state = WorldState {
+            facts: vec![Fact::new(Symbol::Partner, Value::Entity(7))],
+        };
+        let check_partner = |operator, value| {
+            validates_condition(
+                &entity_state,
+                &Condition::new(Symbol::Partner, operator, value),
+            )
+        };
+        assert!(check_partner(Operator::Equal, Value::Entity(7)));
+        assert!(!check_partner(Operator::Equal, Value::Entity(8)));
+        assert!(check_partner(Operator::Not, Value::Entity(8)));
+        assert!(!check_partner(Operator::Greater, Value::Entity(6)));
+        assert!(!check_partner(Operator::Equal, Value::Isize(7)));
+
+        // Ordering non numeric values, mixing value types or unknown symbols never validates
+        assert!(!check(Symbol::HasHouse, Operator::Less, Value::Bool(true)));
+        assert!(!check(Symbol::WoodCount, Operator::Less, f(3.0)));
+        assert!(!check(Symbol::MoneyCount, Operator::Not, Value::Isize(0)));
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let mut state = WorldState {
+            facts: vec![
+                Fact::new(Symbol::WoodCount, Value::Isize(2)),
+                Fact::new(Symbol::Energy, Value::F32(OrderedFloat(10.0))),
+            ],
+        };
+        let f = |v| Value::F32(OrderedFloat(v));
+        let mut apply = |symbol: Symbol, modifier, value| {
+            apply_effects(&[Effect::new(symbol.clone(), modifier, value)], &mut state);
+            find_value(&state, &symbol).cloned()
+        };
+
+        assert_eq!(
+            apply(Symbol::WoodCount, Modifier::Increment, Value::Isize(3)),
+            Some(Value::Isize(5))
+        );
+        assert_eq!(
+            apply(Symbol::WoodCount, Modifier::Decrement, Value::Isize(1)),
+            Some(Value::Isize(4))
+        );
+        assert_eq!(
+            apply(Symbol::WoodCount, Modifier::Multiply, Value::Isize(3)),
+            Some(Value::Isize(12))
+        );
+        assert_eq!(
+            apply(
+                Symbol::WoodCount,
+                Modifier::ClampToRange(Value::Isize(10)),
+                Value::Isize(0)
+            ),
+            Some(Value::Isize(10))
+        );
+        assert_eq!(
+            apply(Symbol::Energy, Modifier::Multiply, f(0.5)),
+            Some(f(5.0))
+        );
+        assert_eq!(
+            apply(Symbol::Energy, Modifier::ClampToRange(f(100.0)), f(20.0)),
+            Some(f(20.0))
+        );
+        assert_eq!(
+            apply(Symbol::Energy, Modifier::SetValue, f(1.0)),
+            Some(f(1.0))
+        );
+
+        // Mismatching types leave the fact unchanged
+        assert_eq!(
+            apply(Symbol::Energy, Modifier::SetValue, Value::Bool(true)),
+            Some(f(1.0))
+        );
+        assert_eq!(
+            apply(Symbol::Energy, Modifier::Increment, Value::Isize(1)),
+            Some(f(1.0))
+        );
+
+        // Missing numeric facts are considered to be zero
+        assert_eq!(
+            apply(Symbol::MoneyCount, Modifier::Decrement, Value::Isize(3)),
+            Some(Value::Isize(-3))
+        );
+        assert_eq!(
+            apply(Symbol::TreeCount, Modifier::Multiply, Value::Isize(3)),
+            Some(Value::Isize(0))
+        );
+        assert_eq!(
+            apply(Symbol::SproutCount, Modifier::SetValue, Value::Isize(3)),
+            Some(Value::Isize(3))
+        );
+        assert_eq!(
+            apply(Symbol::Activity, Modifier::SetValue, Value::Enum("eating")),
+            Some(Value::Enum("eating"))
+        );
+        assert_eq!(
+            apply(Symbol::HasHouse, Modifier::Increment, Value::Bool(true)),
+            None
+        );
+
+        // Entities can be set, but not modified numerically
+        assert_eq!(
+            apply(Symbol::Partner, Modifier::SetValue, Value::Entity(3)),
+            Some(Value::Entity(3))
+        );
+        assert_eq!(
+            apply(Symbol::Partner, Modifier::Increment, Value::Entity(1)),
+            Some(Value::Entity(3))
+        );
+        assert_eq!(
+            apply(Symbol::Partner, Modifier::SetValue, Value::Bool(true)),
+            Some(Value::Entity(3))
+        );
+    }
+
+    #[test]
+    fn test_distance() {
+        let f = |v| Value::F32(OrderedFloat(v));
+        let state_with_energy = |energy| WorldState {
+            facts: vec![
+                Fact::new(Symbol::Energy, f(energy)),
+                Fact::new(Symbol::HasHouse, Value::Bool(false)),
+            ],
+        };
+        let energy_condition = [Condition::new(
+            Symbol::Energy,
+            Operator::GreaterOrEqual,
+            f(100.0),
+        )];
+
+        // Numeric conditions get closer as the value gets closer to the threshold
+        let far = distance(&state_with_energy(0.0), &energy_condition);
+        let near = distance(&state_with_energy(80.0), &energy_condition);
+        assert_eq!(far, 1.0);
+        assert!(0.0 < near && near < far);
+        assert_eq!(distance(&state_with_energy(100.0), &energy_condition), 0.0);
+        assert_eq!(
+            distance(
+                &state_with_energy(100.0),
+                &[Condition::new(Symbol::Energy, Operator::Greater, f(100.0))]
+            ),
+            MIN_CONDITION_DISTANCE
+        );
+
+        // Other conditions count for one each
+        let conditions = [
+            Condition::new(Symbol::HasHouse, Operator::Equal, Value::Bool(true)),
+            Condition::new(Symbol::TreeCount, Operator::GreaterOrEqual, Value::Isize(3)),
+        ];
+        assert_eq!(distance(&state_with_energy(0.0), &conditions), 2.0);
+    }
+
     fn create_goap(patient: bool) -> Goap {
         let mut goap = Goap::new();
 
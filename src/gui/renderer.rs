@@ -1,23 +1,36 @@
 use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::{Point, Rect};
-use sdl2::render::Canvas;
+use sdl2::render::{BlendMode, Canvas};
 use sdl2::ttf::Sdl2TtfContext;
 use sdl2::video::Window;
 
 use crate::World;
+use crate::components::age_component::AgeComponent;
 use crate::components::agent_component::AgentComponent;
 use crate::components::all::*;
 use crate::components::body_component::BodyComponent;
+use crate::components::genome_component::GenomeComponent;
+use crate::components::inbox_component::InboxComponent;
+use crate::components::memory_component::{MemoryComponent, MemoryKind};
 use crate::components::move_to_target_component::MoveToTargetComponent;
 use crate::configuration::Config;
 use crate::ecs::{Ecs, iter_components};
-use crate::ecs::{EntityInfo, iter_entities, to_ctype};
+use crate::ecs::{EntityInfo, iter_entities};
 use crate::gui::text_renderer::TextRenderer;
-use crate::shared_data::biome::humidity;
+use crate::shared_data::biome::{self, Biome};
 use crate::shared_data::body_grid;
+use crate::shared_data::climate;
+use crate::shared_data::soil;
+use crate::systems::utils;
 use std::any::TypeId;
 use std::f64::consts::PI;
 
+const NB_DECISIONS_DISPLAYED: usize = 5;
+// Number of segments used to plot a utility curve
+const NB_CURVE_SEGMENTS: usize = 50;
+// Number of segments used to draw the arc of a vision cone
+const NB_ARC_SEGMENTS: usize = 30;
+
 pub struct Renderer<'ttf> {
     canvas: Canvas<Window>,
     text_renderer: TextRenderer<'ttf>,
@@ -54,7 +67,7 @@ impl<'ttf> Renderer<'ttf> {
 
     pub fn toogle_debug_mode(&mut self) {
         self.debug_mode += 1;
-        self.debug_mode %= 3;
+        self.debug_mode %= 4;
     }
 
     pub fn zoom_in(&mut self, config: &Config) {
@@ -119,7 +132,8 @@ impl<'ttf> Renderer<'ttf> {
 
         match self.debug_mode {
             1 => {
-                self.draw_perlin_noise();
+                self.draw_terrain(config);
+                self.draw_soil_nutrients(config);
             }
             2 => {
                 self.draw_body_grid(config);
@@ -131,9 +145,14 @@ impl<'ttf> Renderer<'ttf> {
 
         let colors = &config.renderer.color;
 
+        // Draw water sources
+        for (body, _) in iter_components!(ecs, (WaterComponent, BodyComponent), (BodyComponent)) {
+            self.draw_square(body, &colors.water_color, body.w());
+        }
+
         // Draw corpses
         for (body, _) in iter_components!(ecs, (CorpseComponent, BodyComponent), (BodyComponent)) {
-            self.draw_square(body, &colors.corpse_color, config.creature.size);
+            self.draw_square(body, &colors.corpse_color, body.w());
         }
 
         // Get all creatures in the order of their entity ID.
@@ -146,15 +165,14 @@ impl<'ttf> Renderer<'ttf> {
         // Draw creatures
         for info in creature_infos {
             // Check what kind of creature this is
-            let color = if ecs.has_component(info.arch_index, &to_ctype!(HerbivorousComponent)) {
-                &colors.herbivorous_color
-            } else {
-                &colors.carnivorous_color
+            let Some(species) = ecs.component::<SpeciesComponent>(&info) else {
+                continue;
             };
+            let color = &species.config(config).color;
             let pos;
             if let Some(body) = ecs.component::<BodyComponent>(&info) {
                 pos = *body;
-                self.draw_square(body, color, config.creature.size);
+                self.draw_square(body, color, body.w());
             } else {
                 continue;
             }
@@ -164,10 +182,7 @@ impl<'ttf> Renderer<'ttf> {
                 self.draw_rec(
                     (
                         pos.x(),
-                        pos.y()
-                            - config.creature.size / 2.0
-                            - config.renderer.bar_height / 2.0
-                            - 5.0,
+                        pos.y() - pos.h() / 2.0 - config.renderer.bar_height / 2.0 - 5.0,
                     ),
                     &colors.health_color,
                     (
@@ -181,10 +196,7 @@ impl<'ttf> Renderer<'ttf> {
                 self.draw_rec(
                     (
                         pos.x(),
-                        pos.y()
-                            - config.creature.size / 2.0
-                            - config.renderer.bar_height * 1.5
-                            - 5.0 * 2.0,
+                        pos.y() - pos.h() / 2.0 - config.renderer.bar_height * 1.5 - 5.0 * 2.0,
                     ),
                     &colors.energy_color,
                     (
@@ -193,6 +205,34 @@ impl<'ttf> Renderer<'ttf> {
                         config.renderer.bar_height,
                     ),
                 );
+
+                // Draw fatigue bar
+                self.draw_rec(
+                    (
+                        pos.x(),
+                        pos.y() - pos.h() / 2.0 - config.renderer.bar_height * 2.5 - 5.0 * 3.0,
+                    ),
+                    &colors.fatigue_color,
+                    (
+                        config.renderer.bar_width * creature.fatigue as f64
+                            / config.creature.max_fatigue as f64,
+                        config.renderer.bar_height,
+                    ),
+                );
+
+                // Draw thirst bar
+                self.draw_rec(
+                    (
+                        pos.x(),
+                        pos.y() - pos.h() / 2.0 - config.renderer.bar_height * 3.5 - 5.0 * 4.0,
+                    ),
+                    &colors.thirst_color,
+                    (
+                        config.renderer.bar_width * creature.thirst as f64
+                            / config.creature.max_thirst as f64,
+                        config.renderer.bar_height,
+                    ),
+                );
             }
         }
 
@@ -225,13 +265,26 @@ impl<'ttf> Renderer<'ttf> {
             }
         }
 
+        self.draw_darkness();
+
+        self.draw_selected_agent_memory(ecs, config);
+        self.draw_selected_agent_perception(ecs, config);
+
+        if self.debug_mode == 3 {
+            self.draw_utility_curves(world, config);
+        }
+
         if let Some(text) = &self.selected_agent_description {
             self.text_renderer
                 .draw_multi_line(text, 0, 0, &mut self.canvas, config);
         }
 
         self.text_renderer.draw_top_right(
-            &world.nb_iterations_computed().to_string(),
+            &format!(
+                "{} | {}",
+                climate::current().description(),
+                world.nb_iterations_computed()
+            ),
             &mut self.canvas,
             config,
         );
@@ -245,27 +298,126 @@ impl<'ttf> Renderer<'ttf> {
             && self.selected_agent_description.is_none()
             && let Some(info) = world.ecs.get_entity_info(agent_entity)
         {
-            Some(
+            Some((
                 world
                     .ecs
                     .component::<AgentComponent>(&info)
                     .unwrap()
                     .clone(),
-            )
+                world.ecs.component::<GenomeComponent>(&info).copied(),
+                world.ecs.component::<MemoryComponent>(&info).cloned(),
+                world.ecs.component::<InboxComponent>(&info).cloned(),
+                world.ecs.component::<AgeComponent>(&info).copied(),
+            ))
         } else {
             None
         };
 
         // Borrow the goap (from world) to get the description
-        if let Some(agent) = agent_opt
+        if let Some((agent, genome, memory, inbox, age)) = agent_opt
+            && let Some(agent_entity) = self.selected_agent
             && let Some(goap) = world.agent_system().map(|a| a.goap())
         {
-            self.selected_agent_description = Some(agent.description(goap));
+            let mut description = agent.description(goap);
+            if let Some(age) = age {
+                description.extend(age.description());
+            }
+            if let Some(genome) = genome {
+                description.extend(genome.description());
+            }
+            if let Some(memory) = memory {
+                description.extend(memory.description());
+            }
+            if let Some(inbox) = inbox {
+                description.extend(inbox.description());
+            }
+
+            // Most recent decisions, if they are recorded
+            let decisions: Vec<String> = goap
+                .agent_decisions(agent_entity)
+                .map(|record| format!("    {}", record.description()))
+                .collect();
+            if !decisions.is_empty() {
+                description.push("DECISIONS".to_string());
+                let skipped = decisions.len().saturating_sub(NB_DECISIONS_DISPLAYED);
+                description.extend(decisions.into_iter().skip(skipped));
+            }
+
+            self.selected_agent_description = Some(description);
+        }
+    }
+
+    /// Plot the utility curves of the goals of the selected agent (bottom left corner of the
+    /// window), along with the current value of their input
+    fn draw_utility_curves(&mut self, world: &World, config: &Config) {
+        let Some(agent_entity) = self.selected_agent else {
+            return;
+        };
+        let Some(info) = world.ecs.get_entity_info(agent_entity) else {
+            return;
+        };
+        let (Some(agent), Some(goap)) = (
+            world.ecs.component::<AgentComponent>(&info),
+            world.agent_system().map(|a| a.goap()),
+        ) else {
+            return;
+        };
+
+        let (w, h) = (
+            config.renderer.utility_plot_width,
+            config.renderer.utility_plot_height,
+        );
+        let mut p_y = self.window_height as i32;
+        for utility_function in goap.utility_functions(agent.goal_set()) {
+            for consideration in utility_function.considerations() {
+                p_y -= h as i32;
+
+                // Background
+                self.canvas
+                    .set_draw_color(Self::to_color(&config.renderer.color.text_background_color));
+                let _ = self.canvas.fill_rect(Rect::new(0, p_y, w, h));
+
+                // Curve
+                let to_pixel = |x: f32, y: f32| {
+                    Point::new(
+                        (x * w as f32) as i32,
+                        p_y + ((1.0 - y) * (h - 1) as f32) as i32,
+                    )
+                };
+                let curve = consideration.curve();
+                let points: Vec<Point> = (0..=NB_CURVE_SEGMENTS)
+                    .map(|i| i as f32 / NB_CURVE_SEGMENTS as f32)
+                    .map(|x| to_pixel(x, curve.score(x)))
+                    .collect();
+                self.canvas
+                    .set_draw_color(Self::to_color(&config.renderer.color.utility_curve_color));
+                let _ = self.canvas.draw_lines(points.as_slice());
+
+                // Current input value
+                if let Some(x) = consideration.input_value(&world.ecs, &info) {
+                    let top = to_pixel(x, 1.0);
+                    let bottom = to_pixel(x, 0.0);
+                    self.canvas
+                        .set_draw_color(Self::to_color(&config.renderer.color.utility_input_color));
+                    let _ = self.canvas.draw_line(top, bottom);
+                }
+
+                self.text_renderer.draw(
+                    consideration.input().name(),
+                    0,
+                    p_y,
+                    &mut self.canvas,
+                    config,
+                );
+            }
         }
     }
 
-    fn draw_perlin_noise(&mut self) {
-        // Perlin noise visualisation for tests
+    // Biomes of the terrain, darker in the low lands. Computed for blocks of pixels, as each
+    // location needs several layers of perlin noise.
+    fn draw_terrain(&mut self, config: &Config) {
+        const BLOCK_SIZE: usize = 4;
+        let colors = &config.renderer.color;
         let texture_creator = self.canvas.texture_creator();
         let mut texture = texture_creator
             .create_texture_streaming(
@@ -274,27 +426,37 @@ impl<'ttf> Renderer<'ttf> {
                 self.window_height,
             )
             .unwrap();
+        let (width, height) = (self.window_width as usize, self.window_height as usize);
         texture
             .with_lock(None, |buffer: &mut [u8], pitch: usize| {
-                for p_x in 0..self.window_width {
-                    for p_y in 0..self.window_height {
-                        let offset = p_y as usize * pitch + p_x as usize * 3;
-
-                        let x = (p_x as f64
+                for block_x in (0..width).step_by(BLOCK_SIZE) {
+                    for block_y in (0..height).step_by(BLOCK_SIZE) {
+                        let x = (block_x as f64
                             - (self.window_width as f64) / 2.0
                             - self.camera_offset_x as f64)
                             / self.zoom;
-                        let y = (p_y as f64
+                        let y = (block_y as f64
                             - (self.window_height as f64) / 2.0
                             - self.camera_offset_y as f64)
                             / self.zoom;
 
-                        let h = humidity(x, y);
-                        let l = (h * 255.0) as u8;
-
-                        buffer[offset] = l;
-                        buffer[offset + 1] = l;
-                        buffer[offset + 2] = l;
+                        let (biome, elevation) = biome::terrain(x, y);
+                        let color = match biome {
+                            Biome::Water => colors.water_color,
+                            Biome::Grassland => colors.grassland_color,
+                            Biome::Forest => colors.forest_color,
+                            Biome::Desert => colors.desert_color,
+                        };
+                        let shade = 0.6 + 0.4 * elevation;
+
+                        for p_x in block_x..usize::min(block_x + BLOCK_SIZE, width) {
+                            for p_y in block_y..usize::min(block_y + BLOCK_SIZE, height) {
+                                let offset = p_y * pitch + p_x * 3;
+                                for c in 0..3 {
+                                    buffer[offset + c] = (color[c] as f64 * shade) as u8;
+                                }
+                            }
+                        }
                     }
                 }
             })
@@ -302,6 +464,26 @@ impl<'ttf> Renderer<'ttf> {
         self.canvas.copy(&texture, None, None).unwrap();
     }
 
+    // Dim the scene according to the time of the day
+    fn draw_darkness(&mut self) {
+        let darkness = ((1.0 - climate::current().brightness()) * 255.0) as u8;
+        if darkness == 0 {
+            return;
+        }
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, darkness));
+        let _ = self.canvas.fill_rect(None);
+        self.canvas.set_blend_mode(BlendMode::None);
+    }
+
+    // Cells of the soil away from the base level of nutrients, sized by their nutrients
+    fn draw_soil_nutrients(&mut self, config: &Config) {
+        for (center, nutrients) in soil::cells() {
+            let size = config.soil.cell_size * nutrients / config.soil.max_nutrients;
+            self.draw_rec(center, &config.renderer.color.nutrient_color, (size, size));
+        }
+    }
+
     fn draw_body_grid(&mut self, config: &Config) {
         let (g_x, g_y, g_w, g_h, g_cell_size, _, _) = body_grid::coords();
 
@@ -335,6 +517,87 @@ impl<'ttf> Renderer<'ttf> {
         }
     }
 
+    /// Link the selected agent to the locations it remembers
+    fn draw_selected_agent_memory(&mut self, ecs: &Ecs, config: &Config) {
+        let Some(info) = self
+            .selected_agent
+            .and_then(|entity| ecs.get_entity_info(entity))
+        else {
+            return;
+        };
+        let (Some(body), Some(memory), Some(species)) = (
+            ecs.component::<BodyComponent>(&info),
+            ecs.component::<MemoryComponent>(&info),
+            ecs.component::<SpeciesComponent>(&info),
+        ) else {
+            return;
+        };
+        let colors = &config.renderer.color;
+        for entry in memory.entries() {
+            let color = match entry.kind {
+                MemoryKind::Plant => &colors.plant_color,
+                MemoryKind::Corpse => &colors.corpse_color,
+                // Prey are linked with the color of their species
+                MemoryKind::Prey => {
+                    match ecs.component_from_entity::<SpeciesComponent>(entry.entity) {
+                        Some(species) => &config.species[species.species].color,
+                        None => continue,
+                    }
+                }
+                MemoryKind::Water => &colors.water_color,
+                MemoryKind::Predator => &colors.danger_color,
+                MemoryKind::Mate => &species.config(config).color,
+            };
+            self.draw_line((body.x(), body.y()), (entry.x, entry.y), color);
+        }
+    }
+
+    /// Draw the bounds of the vision cone of the selected agent
+    fn draw_selected_agent_perception(&mut self, ecs: &Ecs, config: &Config) {
+        let Some(info) = self
+            .selected_agent
+            .and_then(|entity| ecs.get_entity_info(entity))
+        else {
+            return;
+        };
+        let (Some(body), Some(perception), Some(genome)) = (
+            ecs.component::<BodyComponent>(&info),
+            ecs.component::<PerceptionComponent>(&info),
+            ecs.component::<GenomeComponent>(&info),
+        ) else {
+            return;
+        };
+        let distance = utils::perception_distance(ecs, &info, &config.creature);
+        let half_fov = genome.field_of_view.min(2.0 * PI) / 2.0;
+        let point = |angle: f64| {
+            (
+                body.x() + angle.cos() * distance,
+                body.y() + angle.sin() * distance,
+            )
+        };
+        let start = perception.heading - half_fov;
+        let mut previous = point(start);
+        if half_fov < PI {
+            self.draw_line(
+                (body.x(), body.y()),
+                previous,
+                &config.renderer.color.perception_color,
+            );
+        }
+        for i in 1..=NB_ARC_SEGMENTS {
+            let next = point(start + 2.0 * half_fov * i as f64 / NB_ARC_SEGMENTS as f64);
+            self.draw_line(previous, next, &config.renderer.color.perception_color);
+            previous = next;
+        }
+        if half_fov < PI {
+            self.draw_line(
+                (body.x(), body.y()),
+                previous,
+                &config.renderer.color.perception_color,
+            );
+        }
+    }
+
     fn draw_path(&mut self, ecs: &mut Ecs, config: &Config) {
         for (move_to_target_component, ..) in iter_components!(ecs, (), (MoveToTargetComponent)) {
             for i in 0..(move_to_target_component.path().len() as isize - 1) {
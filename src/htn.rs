@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+use crate::goap::{Condition, WorldState, validate_conditions};
+
+/// Task of a hierarchical task network
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Task {
+    // Goal of the agent's goal set, planned by the GOAP planner
+    Goal(usize),
+    // Action of the agent's action set, performed without planning
+    Action(usize),
+    // Task decomposed into sub-tasks by one of its methods
+    Compound(usize),
+}
+
+/// One way of performing a compound task, applicable when its conditions hold on the world state
+pub struct Method {
+    conditions: Vec<Condition>,
+    subtasks: Vec<Task>,
+}
+
+impl Method {
+    pub fn is_applicable(&self, world_state: &WorldState) -> bool {
+        validate_conditions(world_state, &self.conditions)
+    }
+
+    pub fn subtasks(&self) -> &[Task] {
+        &self.subtasks
+    }
+}
+
+pub struct CompoundTask {
+    name: String,
+    // Methods are tried in order, the first applicable one is chosen
+    methods: Vec<Method>,
+}
+
+impl CompoundTask {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            methods: Vec::new(),
+        }
+    }
+
+    pub fn add_method(&mut self, conditions: Vec<Condition>, subtasks: Vec<Task>) {
+        self.methods.push(Method {
+            conditions,
+            subtasks,
+        });
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the index of the first method applicable on the world state, if any
+    pub fn find_method(&self, world_state: &WorldState) -> Option<usize> {
+        self.methods
+            .iter()
+            .position(|m| m.is_applicable(world_state))
+    }
+
+    pub fn method(&self, method: usize) -> &Method {
+        &self.methods[method]
+    }
+}
+
+/// Ordered list of the tasks an agent still has to perform.
+/// Compound tasks are decomposed only when they are reached, so that their method is chosen
+/// according to the world state at that time.
+#[derive(Clone)]
+pub struct Agenda {
+    tasks: VecDeque<Task>,
+    // Goal the agenda comes from, pursued by the agent while the actions of the agenda are performed
+    goal: usize,
+    // Utility of the goal the agenda comes from, used as planning priority for its steps
+    priority: f32,
+}
+
+impl Agenda {
+    pub fn new(task: Task, goal: usize, priority: f32) -> Self {
+        Self {
+            tasks: VecDeque::from([task]),
+            goal,
+            priority,
+        }
+    }
+
+    pub fn goal(&self) -> usize {
+        self.goal
+    }
+
+    pub fn priority(&self) -> f32 {
+        self.priority
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter()
+    }
+
+    pub fn pop(&mut self) -> Option<Task> {
+        self.tasks.pop_front()
+    }
+
+    /// Insert the sub-tasks of a decomposed task before the remaining tasks
+    pub fn push_subtasks(&mut self, subtasks: &[Task]) {
+        for task in subtasks.iter().rev() {
+            self.tasks.push_front(*task);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tasks.clear();
+    }
+}
@@ -2,44 +2,67 @@ mod actions;
 mod algorithms;
 mod components;
 mod configuration;
+mod decision_trace;
 mod ecs;
 mod goals;
 mod goap;
 mod gui;
+mod htn;
+mod sensors;
 mod shared_data;
 mod systems;
 
-use ecs::{Component, Ecs, System, Update};
+use ecs::{Component, Ecs, RESERVED_ENTITY_ID, System, Update};
 use gui::renderer::Renderer;
+use log::{error, info};
 use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use sdl2::mouse::MouseButton;
-use shared_data::biome::humidity;
+use shared_data::biome::{self, humidity};
 use std::{any::TypeId, thread, time};
 
+use components::age_component::AgeComponent;
 use components::agent_component::AgentComponent;
 use components::all::*;
 use components::body_component::BodyComponent;
+use components::genome_component::GenomeComponent;
 use configuration::load_config;
 use shared_data::body_grid;
+use shared_data::climate;
+use shared_data::soil;
+use systems::aging_system::AgingSystem;
+use systems::corpse_decay_system::CorpseDecaySystem;
 use systems::death_system::DeathSystem;
 use systems::digestion_system::DigestionSystem;
 use systems::health_system::HealthSystem;
 use systems::hunger_system::HungerSystem;
+use systems::memory_system::MemorySystem;
+use systems::message_system::MessageSystem;
 use systems::move_to_target_system::MoveToTargetSystem;
 use systems::plant_growth_system::PlantGrowthSystem;
 use systems::reproduction_system::ReproductionSystem;
+use systems::utils;
 
-use crate::actions::all::{EatCorpseAction, EatHerbivorousAction, EatPlantAction};
+use crate::actions::all::{
+    AttackAction, DrinkAction, EatCorpseAction, EatPlantAction, EatPreyAction, MateAction,
+    SleepAction,
+};
 use crate::actions::move_to_actions::{
-    MoveToNearestCorpseAction, MoveToNearestHerbivorousAction, MoveToNearestPlantAction,
+    FleeAction, MoveToMateAction, MoveToNearestCorpseAction, MoveToNearestPlantAction,
+    MoveToNearestPreyAction, MoveToNearestWaterAction,
 };
 use crate::algorithms::rng;
-use crate::configuration::Config;
-use crate::goals::all::ReplenishEnergyGoal;
-use crate::goap::{ActionSet, GoalSet, Goap};
+use crate::configuration::{ActionKind, Config, Food, GoalKind, ReproductionMode};
+use crate::goals::all::{DrinkGoal, FleeGoal, ReplenishEnergyGoal, ReproduceGoal, RestGoal};
+use crate::goap::{ActionSet, Condition, GoalSet, Goap, Operator, Symbol, Value};
+use crate::htn::{CompoundTask, Task};
+use crate::sensors::all::{
+    EnergySensor, FatigueSensor, MatingSensor, ReproductionEnergySensor, ThirstSensor, ThreatSensor,
+};
 use crate::systems::agent_system::AgentSystem;
 
+const DECISION_TRACE_FILE: &str = "decision_trace.jsonl";
+
 pub struct World {
     ecs: Ecs,
     systems: Vec<Box<dyn System>>,
@@ -84,6 +107,7 @@ impl World {
             system.run(&mut self.ecs, config);
         }
         body_grid::purge_deleted_bodies();
+        climate::advance();
         self.nb_iterations_computed += 1;
     }
 
@@ -100,35 +124,196 @@ impl World {
     pub fn nb_iterations_computed(&self) -> usize {
         self.nb_iterations_computed
     }
+
+    pub fn export_decision_trace(&self) {
+        if let Some(trace) = self.agent_system().and_then(|a| a.goap().decision_trace()) {
+            match trace.export(DECISION_TRACE_FILE) {
+                Ok(()) => info!("Decision trace exported to {DECISION_TRACE_FILE}"),
+                Err(e) => error!("Failed to export the decision trace: {e}"),
+            }
+        } else {
+            error!("Decision trace is disabled");
+        }
+    }
 }
 
 // TODO fix doc (cargo doc)
 
+// Move to a mate and mate with it, then feed to recover the energy spent on the offspring (if needed)
+fn breed_task(
+    goap: &mut Goap,
+    move_to_mate_action: usize,
+    mate_action: usize,
+    replenish_energy_goal: usize,
+) -> usize {
+    let mut recover = CompoundTask::new("recover");
+    recover.add_method(
+        vec![Condition::new(
+            Symbol::HasReproductionEnergy,
+            Operator::Equal,
+            Value::Bool(false),
+        )],
+        vec![Task::Goal(replenish_energy_goal)],
+    );
+    recover.add_method(vec![], vec![]);
+    let recover = goap.add_compound_task(recover);
+
+    let mut breed = CompoundTask::new("breed");
+    breed.add_method(
+        vec![],
+        vec![
+            Task::Action(move_to_mate_action),
+            Task::Action(mate_action),
+            Task::Compound(recover),
+        ],
+    );
+    goap.add_compound_task(breed)
+}
+
+// The initial creatures are mutations of the configured traits, for selection to act on
+fn initial_genome(config: &Config, species: usize) -> GenomeComponent {
+    let base = GenomeComponent::new(&config.creature, &config.species[species]);
+    let mut genome = base;
+    genome.mutate(&base, &config.genome);
+    genome
+}
+
 fn create_world(config: &Config) -> World {
     let mut goap = Goap::new();
+    if config.agent.decision_trace {
+        goap.enable_decision_trace(
+            config.agent.decision_trace_max_records,
+            config.agent.decision_trace_max_agents,
+        );
+    }
 
-    let mut gs = GoalSet::new();
-    gs.add(Box::new(ReplenishEnergyGoal::new(config)));
+    // Each species has its own goal set and action set
+    let mating = config.creature.reproduction_mode == ReproductionMode::Mating;
+    let mut species_sets = Vec::new();
+    for (species, species_config) in config.species.iter().enumerate() {
+        let mut gs = GoalSet::new();
+        gs.add_sensor(Box::new(EnergySensor));
+        gs.add_sensor(Box::new(FatigueSensor));
+        gs.add_sensor(Box::new(ThirstSensor));
+        let mut replenish_energy_goal = None;
+        for goal in species_config.goals.iter() {
+            match goal {
+                GoalKind::ReplenishEnergy => {
+                    replenish_energy_goal = Some(gs.len());
+                    gs.add(Box::new(ReplenishEnergyGoal::new(config, species)));
+                }
+                GoalKind::Flee => {
+                    if let Some(flee_goal) = FleeGoal::new(config, species) {
+                        gs.add(Box::new(flee_goal));
+                        gs.add_sensor(Box::new(ThreatSensor));
+                    }
+                }
+                GoalKind::Rest => gs.add(Box::new(RestGoal::new(config, species))),
+                GoalKind::Drink => gs.add(Box::new(DrinkGoal::new(config, species))),
+                GoalKind::Reproduce => {}
+            }
+        }
+        // Added last, so that its index is known by the mate action
+        let reproduce_goal =
+            (mating && species_config.goals.contains(&GoalKind::Reproduce)).then(|| gs.len());
+
+        let mut act_s = ActionSet::new();
+        let (mut move_to_mate_action, mut mate_action) = (None, None);
+        let diet = |food| species_config.diet(food).unwrap();
+        for action in species_config.actions.iter() {
+            match action {
+                ActionKind::MoveToPlant => act_s.add(Box::new(MoveToNearestPlantAction::new())),
+                ActionKind::EatPlant => {
+                    act_s.add(Box::new(EatPlantAction::new(config, diet(Food::Plant))))
+                }
+                ActionKind::MoveToCorpse => act_s.add(Box::new(MoveToNearestCorpseAction::new())),
+                ActionKind::EatCorpse => {
+                    act_s.add(Box::new(EatCorpseAction::new(config, diet(Food::Corpse))))
+                }
+                ActionKind::MoveToPrey => act_s.add(Box::new(MoveToNearestPreyAction::new())),
+                ActionKind::Attack => act_s.add(Box::new(AttackAction::new())),
+                ActionKind::EatPrey => {
+                    act_s.add(Box::new(EatPreyAction::new(config, diet(Food::Prey))))
+                }
+                ActionKind::Flee => act_s.add(Box::new(FleeAction::new())),
+                ActionKind::Sleep => act_s.add(Box::new(SleepAction::new())),
+                ActionKind::MoveToWater => act_s.add(Box::new(MoveToNearestWaterAction::new())),
+                ActionKind::Drink => act_s.add(Box::new(DrinkAction::new())),
+                ActionKind::MoveToMate if reproduce_goal.is_some() => {
+                    move_to_mate_action = Some(act_s.len());
+                    act_s.add(Box::new(MoveToMateAction::new()));
+                }
+                ActionKind::Mate => {
+                    if let Some(reproduce_goal) = reproduce_goal {
+                        mate_action = Some(act_s.len());
+                        act_s.add(Box::new(MateAction::new(config, reproduce_goal)));
+                    }
+                }
+                ActionKind::MoveToMate => {}
+            }
+        }
 
-    let mut h_as = ActionSet::new();
-    h_as.add(Box::new(MoveToNearestPlantAction::new()));
-    h_as.add(Box::new(EatPlantAction::new(config)));
+        // The configuration is checked when loaded: a species that reproduces can mate and feed
+        if reproduce_goal.is_some() {
+            let breed = breed_task(
+                &mut goap,
+                move_to_mate_action.unwrap(),
+                mate_action.unwrap(),
+                replenish_energy_goal.unwrap(),
+            );
+            gs.add_with_task(Box::new(ReproduceGoal::new(config, species)), breed);
+            gs.add_sensor(Box::new(MatingSensor));
+            gs.add_sensor(Box::new(ReproductionEnergySensor));
+        }
 
-    let herbivorous_goal_set = goap.add_goal_set(gs);
-    let herbivorous_action_set_len = h_as.len();
-    let herbivorous_action_set = goap.add_action_set(h_as);
+        let goal_set = goap.add_goal_set(gs);
+        let action_set_len = act_s.len();
+        let action_set = goap.add_action_set(act_s);
+        species_sets.push((goal_set, action_set, action_set_len));
+    }
 
-    let mut c_as = ActionSet::new();
-    c_as.add(Box::new(MoveToNearestCorpseAction::new()));
-    c_as.add(Box::new(EatCorpseAction::new(config)));
-    c_as.add(Box::new(MoveToNearestHerbivorousAction::new()));
-    c_as.add(Box::new(EatHerbivorousAction::new(config)));
+    let mut world = World::new();
 
-    let carnivorous_goal_set = herbivorous_goal_set;
-    let carnivorous_action_set_len = c_as.len();
-    let carnivorous_action_set = goap.add_action_set(c_as);
+    // Water sources are placed first, in the most humid places on land
+    let mut nb_water = 0;
+    for _ in 0..config.water.max_placement_attempts {
+        if nb_water >= config.water.nb {
+            break;
+        }
+        let x = rng::random_range(
+            config.body_domain_initial_width / -2.0,
+            config.body_domain_initial_width / 2.0,
+        );
+        let y = rng::random_range(
+            config.body_domain_initial_height / -2.0,
+            config.body_domain_initial_height / 2.0,
+        );
+        let body = BodyComponent::new_not_traversable(x, y, config.water.size, config.water.size);
+        if humidity(x, y) >= config.water.min_humidity
+            && !biome::is_water(x, y)
+            && !body_grid::collides(RESERVED_ENTITY_ID, &body)
+        {
+            world.create_entity_with(&[&WaterComponent, &body]);
+            nb_water += 1;
+        }
+    }
 
-    let mut world = World::new();
+    // The lakes can be drunk from along their shores
+    let (half_width, half_height) = (
+        config.body_domain_initial_width / 2.0,
+        config.body_domain_initial_height / 2.0,
+    );
+    for (x, y) in biome::shores(
+        (-half_width, -half_height),
+        (half_width, half_height),
+        config.water.shore_spacing,
+    ) {
+        let size = config.water.shore_size;
+        let body = BodyComponent::new_not_traversable(x, y, size, size);
+        if !body_grid::collides(RESERVED_ENTITY_ID, &body) {
+            world.create_entity_with(&[&WaterComponent, &body]);
+        }
+    }
 
     for _ in 0..config.plant_nb {
         // Plants start as seed, which have no collision. They gain collision later on.
@@ -143,51 +328,37 @@ fn create_world(config: &Config) -> World {
         ]);
     }
 
-    for _ in 0..config.herbivorous_nb {
-        world.create_entity_with(&[
-            &CreatureComponent::new(&config.creature),
-            &BodyComponent::new_rand_pos_not_traversable(
+    for (species, (goal_set, action_set, action_set_len)) in species_sets.into_iter().enumerate() {
+        for _ in 0..config.species[species].initial_nb {
+            let genome = initial_genome(config, species);
+            let body = BodyComponent::new_rand_pos_not_traversable(
                 config.body_domain_initial_width,
                 config.body_domain_initial_height,
-                config.creature.size,
-                config.creature.size,
-            ),
-            &HerbivorousComponent::new(),
-            &AgentComponent::new(
-                herbivorous_goal_set,
-                herbivorous_action_set,
-                herbivorous_action_set_len,
-            ),
-        ]);
-    }
-
-    for _ in 0..config.carnivorous_nb {
-        world.create_entity_with(&[
-            &CreatureComponent::new(&config.creature),
-            &BodyComponent::new_rand_pos_not_traversable(
-                config.body_domain_initial_width,
-                config.body_domain_initial_height,
-                config.creature.size,
-                config.creature.size,
-            ),
-            &CarnivorousComponent::new(),
-            &AgentComponent::new(
-                carnivorous_goal_set,
-                carnivorous_action_set,
-                carnivorous_action_set_len,
-            ),
-        ]);
+                genome.size,
+                genome.size,
+            );
+            world
+                .ecs
+                .apply(vec![Update::Create(utils::creature_components(
+                    config,
+                    SpeciesComponent::new(species),
+                    genome,
+                    body,
+                    AgeComponent::new_adult(config.species[species].lifespan, &config.aging),
+                    AgentComponent::new(goal_set, action_set, action_set_len),
+                ))]);
+        }
     }
 
     #[allow(clippy::reversed_empty_ranges)]
     for _ in 0..config.corpse_nb {
         world.create_entity_with(&[
-            &CorpseComponent::new(),
+            &CorpseComponent::new(config.corpse_energy(config.max_creature_size(), 0.0)),
             &BodyComponent::new_rand_pos_not_traversable(
                 config.body_domain_initial_width,
                 config.body_domain_initial_height,
-                config.creature.size,
-                config.creature.size,
+                config.max_creature_size(),
+                config.max_creature_size(),
             ),
         ]);
     }
@@ -206,18 +377,18 @@ fn create_world(config: &Config) -> World {
 
     world.add_system(Box::new(DeathSystem));
     world.add_system(Box::new(HealthSystem));
+    world.add_system(Box::new(AgingSystem));
+    world.add_system(Box::new(CorpseDecaySystem));
     world.add_system(Box::new(PlantGrowthSystem));
-    world.add_system(Box::new(ReproductionSystem::new(
-        herbivorous_goal_set,
-        herbivorous_action_set,
-        herbivorous_action_set_len,
-        carnivorous_goal_set,
-        carnivorous_action_set,
-        carnivorous_action_set_len,
-    )));
+    // When reproducing by mating, the agents decide to reproduce themselves
+    if config.creature.reproduction_mode == ReproductionMode::Asexual {
+        world.add_system(Box::new(ReproductionSystem));
+    }
     world.add_system(Box::new(HungerSystem));
     world.add_system(Box::new(MoveToTargetSystem));
     world.add_system(Box::new(DigestionSystem));
+    world.add_system(Box::new(MessageSystem));
+    world.add_system(Box::new(MemorySystem::new()));
     world.add_system(Box::new(AgentSystem::new(goap)));
 
     world
@@ -229,6 +400,9 @@ fn main() {
     let mut config = load_config("config.toml");
     rng::init(&config);
     body_grid::init(&config);
+    soil::init(&config);
+    climate::init(&config);
+    biome::init(&config);
     let mut world = create_world(&config);
     let default_ms_per_iteration = config.ms_per_iteration;
 
@@ -277,6 +451,9 @@ fn main() {
                     Keycode::I => {
                         world.force_iterate(&config);
                     }
+                    Keycode::X => {
+                        world.export_decision_trace();
+                    }
                     Keycode::T => {
                         config.ms_per_iteration = if config.ms_per_iteration == 0 {
                             default_ms_per_iteration
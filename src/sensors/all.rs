@@ -0,0 +1,97 @@
+use ordered_float::OrderedFloat;
+
+use crate::{
+    components::{
+        all::{CreatureComponent, SpeciesComponent},
+        body_component::BodyComponent,
+        genome_component::GenomeComponent,
+    },
+    configuration::Config,
+    ecs::{Ecs, EntityInfo},
+    goap::{Sensor, Symbol, Value},
+    shared_data::climate,
+    systems::utils,
+};
+
+/// The agent is safe when it neither sees nor has been warned about any predator nearby
+pub struct ThreatSensor;
+impl Sensor for ThreatSensor {
+    fn sense(&self, ecs: &Ecs, info: &EntityInfo, config: &Config) -> (Symbol, Value) {
+        let is_safe = ecs.component::<BodyComponent>(info).is_none_or(|body| {
+            utils::known_threats(
+                ecs,
+                info,
+                body,
+                &utils::predators_of(ecs, info, config),
+                &config.creature,
+            )
+            .is_empty()
+        });
+        (Symbol::IsSafe, Value::Bool(is_safe))
+    }
+}
+
+pub struct EnergySensor;
+impl Sensor for EnergySensor {
+    fn sense(&self, ecs: &Ecs, info: &EntityInfo, _config: &Config) -> (Symbol, Value) {
+        let energy = ecs
+            .component::<CreatureComponent>(info)
+            .map_or(0.0, |creature| creature.energy);
+        (Symbol::Energy, Value::F32(OrderedFloat(energy)))
+    }
+}
+
+/// The agent is rested when its fatigue is low enough, or fully recovered outside of the
+/// activity hours of its species
+pub struct FatigueSensor;
+impl Sensor for FatigueSensor {
+    fn sense(&self, ecs: &Ecs, info: &EntityInfo, config: &Config) -> (Symbol, Value) {
+        let is_active = ecs
+            .component::<SpeciesComponent>(info)
+            .is_none_or(|species| climate::current().is_active(species.config(config).activity));
+        let rested_fatigue = if is_active {
+            config.creature.rested_fatigue
+        } else {
+            0.0
+        };
+        let is_rested = ecs
+            .component::<CreatureComponent>(info)
+            .is_none_or(|creature| creature.fatigue <= rested_fatigue);
+        (Symbol::IsRested, Value::Bool(is_rested))
+    }
+}
+
+/// The agent is hydrated when its thirst is low enough
+pub struct ThirstSensor;
+impl Sensor for ThirstSensor {
+    fn sense(&self, ecs: &Ecs, info: &EntityInfo, config: &Config) -> (Symbol, Value) {
+        let is_hydrated = ecs
+            .component::<CreatureComponent>(info)
+            .is_none_or(|creature| creature.thirst <= config.creature.quenched_thirst);
+        (Symbol::IsHydrated, Value::Bool(is_hydrated))
+    }
+}
+
+/// Mating is never over: an agent that already mated can mate again
+pub struct MatingSensor;
+impl Sensor for MatingSensor {
+    fn sense(&self, _ecs: &Ecs, _info: &EntityInfo, _config: &Config) -> (Symbol, Value) {
+        (Symbol::HasMated, Value::Bool(false))
+    }
+}
+
+/// The agent has enough energy to reproduce, according to the threshold of its genome
+pub struct ReproductionEnergySensor;
+impl Sensor for ReproductionEnergySensor {
+    fn sense(&self, ecs: &Ecs, info: &EntityInfo, config: &Config) -> (Symbol, Value) {
+        let threshold = ecs
+            .component::<GenomeComponent>(info)
+            .map_or(config.creature.reprod_energy_threshold, |genome| {
+                genome.reprod_energy_threshold
+            });
+        let has_energy = ecs
+            .component::<CreatureComponent>(info)
+            .is_some_and(|creature| creature.energy >= threshold);
+        (Symbol::HasReproductionEnergy, Value::Bool(has_energy))
+    }
+}
@@ -1,6 +1,297 @@
 use crate::algorithms::perlin_noise::perlin_noise;
+use crate::configuration::{BiomeConfig, Config};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/* Procedural terrain, made of several layers of perlin noise (humidity, elevation, temperature)
+ * from which the biome of each location is derived.
+ *
+ * The biome gives the cost of moving through a location. Water cannot be entered, but lakes can
+ * be drunk from at their shores.
+ * The noise is costly to compute: the terrain is cached by cells, uniform within each cell.
+ */
+
+thread_local! {
+    static TERRAIN: RefCell<Option<Terrain>> = const { RefCell::new(None) }
+}
+
+// The cells of the initial domain are computed upfront, the others on demand
+pub fn init(config: &Config) {
+    let mut terrain = Terrain {
+        config: config.biome,
+        cells: HashMap::new(),
+    };
+    let cell_size = config.biome.cell_size;
+    let nb_cells_x = (config.body_domain_initial_width / cell_size).ceil() as i64;
+    let nb_cells_y = (config.body_domain_initial_height / cell_size).ceil() as i64;
+    for i in -nb_cells_x / 2..=nb_cells_x / 2 {
+        for j in -nb_cells_y / 2..=nb_cells_y / 2 {
+            terrain.cell(i as f64 * cell_size, j as f64 * cell_size);
+        }
+    }
+    TERRAIN.with_borrow_mut(|t| *t = Some(terrain));
+}
+
+/// Terrain without water, so that bodies can be placed anywhere (for the tests)
+#[cfg(test)]
+pub fn init_without_water(config: &Config) {
+    let terrain = Terrain {
+        config: BiomeConfig {
+            water_level: 0.0,
+            ..config.biome
+        },
+        cells: HashMap::new(),
+    };
+    TERRAIN.with_borrow_mut(|t| *t = Some(terrain));
+}
+
+struct Terrain {
+    config: BiomeConfig,
+    // Biome and elevation of the cells computed so far, by cell coordinates
+    cells: HashMap<(i64, i64), (Biome, f64)>,
+}
+
+impl Terrain {
+    /// Biome and elevation of the cell containing this location (taken at the center of the cell)
+    fn cell(&mut self, x: f64, y: f64) -> (Biome, f64) {
+        let cell_size = self.config.cell_size;
+        let (cell_x, cell_y) = ((x / cell_size).floor(), (y / cell_size).floor());
+        let config = &self.config;
+        *self
+            .cells
+            .entry((cell_x as i64, cell_y as i64))
+            .or_insert_with(|| {
+                let (x, y) = ((cell_x + 0.5) * cell_size, (cell_y + 0.5) * cell_size);
+                (classify(x, y, config), elevation(x, y))
+            })
+    }
+}
+
+// Offsets of the layers, so that they are not correlated to each other
+const ELEVATION_OFFSET: f64 = 104_729.0;
+const TEMPERATURE_OFFSET: f64 = -52_361.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Water,
+    Grassland,
+    Forest,
+    Desert,
+}
 
 /// Return the humidity level in [0; 1] at this location
 pub fn humidity(x: f64, y: f64) -> f64 {
     perlin_noise(x, y, 0.0005, 0.5) + 0.5
 }
+
+/// Return the elevation in [0; 1] at this location (large landforms, with finer details)
+fn elevation(x: f64, y: f64) -> f64 {
+    let (x, y) = (x + ELEVATION_OFFSET, y + ELEVATION_OFFSET);
+    let noise = perlin_noise(x, y, 0.0003, 0.35)
+        + perlin_noise(x, y, 0.0012, 0.1)
+        + perlin_noise(x, y, 0.005, 0.05);
+    (noise + 0.5).clamp(0.0, 1.0)
+}
+
+/// Return the temperature level in [0; 1] at this location (colder in altitude)
+fn temperature(x: f64, y: f64) -> f64 {
+    let noise = perlin_noise(x + TEMPERATURE_OFFSET, y + TEMPERATURE_OFFSET, 0.0004, 0.5);
+    (noise + 0.5 - 0.5 * (elevation(x, y) - 0.5)).clamp(0.0, 1.0)
+}
+
+pub fn biome(x: f64, y: f64) -> Biome {
+    terrain(x, y).0
+}
+
+/// Biome and elevation of the terrain at this location
+pub fn terrain(x: f64, y: f64) -> (Biome, f64) {
+    TERRAIN.with_borrow_mut(|terrain| terrain.as_mut().unwrap().cell(x, y))
+}
+
+pub fn is_water(x: f64, y: f64) -> bool {
+    biome(x, y) == Biome::Water
+}
+
+/// Return the points of a grid of the given spacing over the area that are on land, with water
+/// at most one spacing away (horizontally or vertically)
+pub fn shores(
+    (min_x, min_y): (f64, f64),
+    (max_x, max_y): (f64, f64),
+    spacing: f64,
+) -> Vec<(f64, f64)> {
+    let nb_x = ((max_x - min_x) / spacing).floor() as usize;
+    let nb_y = ((max_y - min_y) / spacing).floor() as usize;
+    let mut shores = Vec::new();
+    for i in 0..=nb_x {
+        for j in 0..=nb_y {
+            let (x, y) = (min_x + i as f64 * spacing, min_y + j as f64 * spacing);
+            if !is_water(x, y)
+                && [
+                    (spacing, 0.0),
+                    (-spacing, 0.0),
+                    (0.0, spacing),
+                    (0.0, -spacing),
+                ]
+                .iter()
+                .any(|(dx, dy)| is_water(x + dx, y + dy))
+            {
+                shores.push((x, y));
+            }
+        }
+    }
+    shores
+}
+
+/// Cost of moving through this location (speeds are divided by it), None for water
+pub fn movement_cost(x: f64, y: f64) -> Option<f64> {
+    TERRAIN.with_borrow_mut(|terrain| {
+        let terrain = terrain.as_mut().unwrap();
+        cost(terrain.cell(x, y).0, &terrain.config)
+    })
+}
+
+/// Average cost of moving along the edge from a to b, None if it goes into water.
+/// Water is only checked after a, so that an edge can lead out of water.
+pub fn edge_cost((ax, ay): (f64, f64), (bx, by): (f64, f64)) -> Option<f64> {
+    TERRAIN.with_borrow_mut(|terrain| {
+        let terrain = terrain.as_mut().unwrap();
+        let config = terrain.config;
+        let length = ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt();
+        let nb_samples = ((length / config.water_check_step).ceil() as usize).max(1);
+        let starts_in_water = terrain.cell(ax, ay).0 == Biome::Water;
+
+        let mut total_cost = 0.0;
+        for i in 1..=nb_samples {
+            let t = i as f64 / nb_samples as f64;
+            let (biome, _) = terrain.cell(ax + (bx - ax) * t, ay + (by - ay) * t);
+            total_cost += match cost(biome, &config) {
+                Some(cost) => cost,
+                // Leaving the water, at the cost of the hardest land
+                None if starts_in_water => config.forest_cost.max(config.desert_cost),
+                None => return None,
+            };
+        }
+        Some(total_cost / nb_samples as f64)
+    })
+}
+
+fn classify(x: f64, y: f64, config: &BiomeConfig) -> Biome {
+    if elevation(x, y) < config.water_level {
+        return Biome::Water;
+    }
+    let humidity = humidity(x, y);
+    if temperature(x, y) - humidity > config.desert_dryness {
+        Biome::Desert
+    } else if humidity > config.forest_humidity {
+        Biome::Forest
+    } else {
+        Biome::Grassland
+    }
+}
+
+fn cost(biome: Biome, config: &BiomeConfig) -> Option<f64> {
+    match biome {
+        Biome::Water => None,
+        Biome::Grassland => Some(config.grassland_cost),
+        Biome::Forest => Some(config.forest_cost),
+        Biome::Desert => Some(config.desert_cost),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        configuration::{BiomeConfig, test_config},
+        shared_data::biome::{
+            self, Biome, Terrain, classify, elevation, humidity, is_water, shores, temperature,
+        },
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_biomes() {
+        let config = BiomeConfig {
+            water_level: 0.3,
+            desert_dryness: 0.2,
+            forest_humidity: 0.65,
+            grassland_cost: 1.0,
+            forest_cost: 1.5,
+            desert_cost: 1.2,
+            water_check_step: 20.0,
+            cell_size: 20.0,
+        };
+
+        // The layers stay in range, and the biomes follow them
+        let mut biomes = Vec::new();
+        for i in -50..50 {
+            for j in -50..50 {
+                let (x, y) = (i as f64 * 97.0, j as f64 * 89.0);
+                for layer in [humidity(x, y), elevation(x, y), temperature(x, y)] {
+                    assert!((0.0..=1.0).contains(&layer));
+                }
+                let biome = classify(x, y, &config);
+                match biome {
+                    Biome::Water => assert!(elevation(x, y) < config.water_level),
+                    Biome::Forest => assert!(humidity(x, y) > config.forest_humidity),
+                    _ => assert!(elevation(x, y) >= config.water_level),
+                }
+                biomes.push(biome);
+            }
+        }
+
+        // All the biomes exist
+        for biome in [Biome::Water, Biome::Grassland, Biome::Forest, Biome::Desert] {
+            assert!(biomes.contains(&biome), "No {biome:?} found");
+        }
+
+        // The cached terrain is the one at the center of the cells
+        let mut terrain = Terrain {
+            config,
+            cells: HashMap::new(),
+        };
+        for i in -50..50 {
+            let (x, y) = (i as f64 * 97.0 + 3.0, i as f64 * -89.0 + 7.0);
+            let (center_x, center_y) = (
+                ((x / 20.0).floor() + 0.5) * 20.0,
+                ((y / 20.0).floor() + 0.5) * 20.0,
+            );
+            assert_eq!(
+                terrain.cell(x, y),
+                (
+                    classify(center_x, center_y, &config),
+                    elevation(center_x, center_y)
+                )
+            );
+        }
+        assert_eq!(terrain.cells.len(), 100);
+    }
+
+    #[test]
+    fn test_shores() {
+        let config = test_config();
+        let spacing = config.water.shore_spacing;
+        let area = ((-5000.0, -5000.0), (5000.0, 5000.0));
+
+        // The shores are on land, next to water
+        biome::init(&config);
+        let found = shores(area.0, area.1, spacing);
+        assert!(!found.is_empty());
+        for (x, y) in found {
+            assert!(!is_water(x, y));
+            assert!(
+                [
+                    (spacing, 0.0),
+                    (-spacing, 0.0),
+                    (0.0, spacing),
+                    (0.0, -spacing)
+                ]
+                .iter()
+                .any(|(dx, dy)| is_water(x + dx, y + dy))
+            );
+        }
+
+        // Without water, there is no shore
+        biome::init_without_water(&config);
+        assert!(shores(area.0, area.1, spacing).is_empty());
+    }
+}
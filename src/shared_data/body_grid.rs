@@ -24,6 +24,12 @@ pub fn init(config: &Config) {
     BODY_GRID.with_borrow_mut(|grid| *grid = Some(BodyGrid::new(config)));
 }
 
+/// Grid of the given area, without configuration (for the tests)
+#[cfg(test)]
+pub fn init_with_area(min: (f64, f64), max: (f64, f64), cell_size: f64) {
+    BODY_GRID.with_borrow_mut(|grid| *grid = Some(BodyGrid::with_area(min, max, cell_size)));
+}
+
 enum Direction {
     Up,
     Right,
@@ -203,13 +209,16 @@ impl BodyGrid {
     }
 
     pub fn new(config: &Config) -> Self {
-        let max_entity_size = config.creature.size;
+        let max_entity_size = config.max_creature_size();
         let min_x = (-(config.renderer.screen_width as f64) - max_entity_size) / 2.0;
         let max_x = (config.renderer.screen_width as f64 + max_entity_size) / 2.0;
         let min_y = (-(config.renderer.screen_height as f64) - max_entity_size) / 2.0;
         let max_y = (config.renderer.screen_height as f64 + max_entity_size) / 2.0;
         let cell_size = max_entity_size * config.collision.cell_size_factor;
+        Self::with_area((min_x, min_y), (max_x, max_y), cell_size)
+    }
 
+    fn with_area((min_x, min_y): (f64, f64), (max_x, max_y): (f64, f64), cell_size: f64) -> Self {
         // Compute the minimal size for the grid (float)
         let mut w = (max_x - min_x).abs();
         let mut h = (max_y - min_y).abs();
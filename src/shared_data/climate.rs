@@ -0,0 +1,216 @@
+use crate::configuration::{Activity, ClimateConfig, Config, DayConfig};
+use std::cell::RefCell;
+use std::f64::consts::PI;
+
+/* Global climate, following a cycle of seasons and a cycle of days and nights.
+ *
+ * A seasonal wave goes from -1 (middle of the winter) to 1 (middle of the summer), and modulates
+ * the temperature, the humidity, the growth of the plants and the hunger of the creatures.
+ *
+ * Each day starts at sunrise, and the night starts halfway through it.
+ */
+
+thread_local! {
+    static CLIMATE: RefCell<Option<Climate>> = const { RefCell::new(None) }
+}
+
+pub fn init(config: &Config) {
+    CLIMATE.with_borrow_mut(|climate| *climate = Some(Climate::new(config.climate, config.day)));
+}
+
+/// Move the climate one tick forward
+pub fn advance() {
+    CLIMATE.with_borrow_mut(|climate| climate.as_mut().unwrap().tick += 1);
+}
+
+/// Return the current climate
+pub fn current() -> Climate {
+    CLIMATE.with_borrow(|climate| climate.unwrap())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+#[derive(Clone, Copy)]
+pub struct Climate {
+    config: ClimateConfig,
+    day_config: DayConfig,
+    tick: usize,
+}
+
+impl Climate {
+    fn new(config: ClimateConfig, day_config: DayConfig) -> Self {
+        Self {
+            config,
+            day_config,
+            tick: 0,
+        }
+    }
+
+    /// Fraction of the current year elapsed, in [0; 1[ (the year starts with the spring)
+    fn year_progress(&self) -> f64 {
+        (self.tick % self.config.year_ticks) as f64 / self.config.year_ticks as f64
+    }
+
+    // 1 in the middle of the summer, -1 in the middle of the winter
+    fn wave(&self) -> f64 {
+        (2.0 * PI * (self.year_progress() - 0.125)).sin()
+    }
+
+    pub fn season(&self) -> Season {
+        match (self.year_progress() * 4.0) as usize {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    pub fn temperature(&self) -> f64 {
+        self.config.temperature_mean + self.config.temperature_amplitude * self.wave()
+    }
+
+    /// Multiplies the humidity of the biome (summers are drier)
+    pub fn humidity_factor(&self) -> f64 {
+        1.0 - self.config.humidity_amplitude * self.wave()
+    }
+
+    /// Multiplies the growth rate of the plants (faster in summer)
+    pub fn growth_factor(&self) -> f64 {
+        1.0 + self.config.growth_amplitude * self.wave()
+    }
+
+    /// Multiplies the seed production rate of the plants (higher in summer)
+    pub fn seed_factor(&self) -> f64 {
+        1.0 + self.config.seed_amplitude * self.wave()
+    }
+
+    /// Multiplies the hunger rate of the creatures (higher in winter)
+    pub fn hunger_factor(&self) -> f32 {
+        (1.0 - self.config.hunger_amplitude * self.wave()) as f32
+    }
+
+    /// Fraction of the current day elapsed, in [0; 1[
+    fn day_progress(&self) -> f64 {
+        (self.tick % self.day_config.day_ticks) as f64 / self.day_config.day_ticks as f64
+    }
+
+    pub fn is_day(&self) -> bool {
+        self.day_progress() < 0.5
+    }
+
+    /// Light level, in [0; 1]: 1 for most of the day, 0 for most of the night,
+    /// changing progressively around sunrise and sunset
+    pub fn daylight(&self) -> f64 {
+        (0.5 + (2.0 * PI * self.day_progress()).sin()).clamp(0.0, 1.0)
+    }
+
+    /// Check if the creatures with this activity cycle are in their activity hours
+    pub fn is_active(&self, activity: Activity) -> bool {
+        match activity {
+            Activity::Diurnal => self.is_day(),
+            Activity::Nocturnal => !self.is_day(),
+        }
+    }
+
+    /// Multiplies the perception distances (short sighted in the dark, without night vision)
+    pub fn perception_factor(&self, night_vision: bool) -> f64 {
+        if night_vision {
+            return 1.0;
+        }
+        let night_factor = self.day_config.night_perception_factor;
+        night_factor + (1.0 - night_factor) * self.daylight()
+    }
+
+    /// For renderer: brightness of the scene, in [0; 1]
+    pub fn brightness(&self) -> f64 {
+        let night_brightness = self.day_config.night_brightness;
+        night_brightness + (1.0 - night_brightness) * self.daylight()
+    }
+
+    /// For renderer
+    pub fn description(&self) -> String {
+        format!(
+            "{:?}, {:.0} C, {}",
+            self.season(),
+            self.temperature(),
+            if self.is_day() { "day" } else { "night" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        configuration::{Activity, ClimateConfig, DayConfig},
+        shared_data::climate::{Climate, Season},
+    };
+
+    fn climate() -> Climate {
+        Climate::new(
+            ClimateConfig {
+                year_ticks: 800,
+                temperature_mean: 15.0,
+                temperature_amplitude: 10.0,
+                humidity_amplitude: 0.2,
+                growth_amplitude: 0.5,
+                seed_amplitude: 0.5,
+                hunger_amplitude: 0.5,
+            },
+            DayConfig {
+                day_ticks: 100,
+                night_perception_factor: 0.5,
+                off_hours_rest_utility: 10.0,
+                night_brightness: 0.2,
+            },
+        )
+    }
+
+    #[test]
+    fn test_seasons() {
+        let mut climate = climate();
+        assert_eq!(climate.season(), Season::Spring);
+
+        // Middle of the summer
+        climate.tick = 300;
+        assert_eq!(climate.season(), Season::Summer);
+        assert!((climate.temperature() - 25.0).abs() < 1e-9);
+        assert!((climate.growth_factor() - 1.5).abs() < 1e-9);
+        assert!((climate.humidity_factor() - 0.8).abs() < 1e-9);
+
+        climate.tick = 500;
+        assert_eq!(climate.season(), Season::Autumn);
+
+        // Middle of the winter, of the next year
+        climate.tick = 1500;
+        assert_eq!(climate.season(), Season::Winter);
+        assert!((climate.temperature() - 5.0).abs() < 1e-9);
+        assert!((climate.hunger_factor() - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_days_and_nights() {
+        let mut climate = climate();
+
+        // Middle of the day
+        climate.tick = 225;
+        assert!(climate.is_day());
+        assert!(climate.is_active(Activity::Diurnal));
+        assert_eq!(climate.daylight(), 1.0);
+        assert_eq!(climate.perception_factor(false), 1.0);
+
+        // Middle of the night
+        climate.tick = 275;
+        assert!(!climate.is_day());
+        assert!(climate.is_active(Activity::Nocturnal));
+        assert_eq!(climate.daylight(), 0.0);
+        assert_eq!(climate.perception_factor(false), 0.5);
+        assert_eq!(climate.perception_factor(true), 1.0);
+        assert!((climate.brightness() - 0.2).abs() < 1e-9);
+    }
+}
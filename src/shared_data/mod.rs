@@ -1,2 +1,4 @@
 pub mod biome;
 pub mod body_grid;
+pub mod climate;
+pub mod soil;
@@ -0,0 +1,146 @@
+use crate::configuration::{Config, SoilConfig};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/* Nutrients of the soil, consumed by growing plants and brought back by decaying corpses and
+ * droppings.
+ *
+ * The world is split in square cells (independent from the body grid). Every cell starts with
+ * the base level of nutrients, and slowly goes back to it (either regenerating or being washed
+ * away). Only the cells away from the base level are stored, as the world has no fixed bounds.
+ */
+
+thread_local! {
+    static SOIL: RefCell<Option<Soil>> = const { RefCell::new(None) }
+}
+
+pub fn init(config: &Config) {
+    SOIL.with_borrow_mut(|soil| *soil = Some(Soil::new(config.soil)));
+}
+
+/// Add nutrients to the soil at this location
+pub fn fertilize(x: f64, y: f64, amount: f64) {
+    SOIL.with_borrow_mut(|soil| soil.as_mut().unwrap().fertilize(x, y, amount));
+}
+
+/// Remove nutrients from the soil at this location, and return the amount actually removed
+pub fn consume(x: f64, y: f64, amount: f64) -> f64 {
+    SOIL.with_borrow_mut(|soil| soil.as_mut().unwrap().consume(x, y, amount))
+}
+
+/// Return the nutrients of the soil at this location
+pub fn nutrients(x: f64, y: f64) -> f64 {
+    SOIL.with_borrow(|soil| soil.as_ref().unwrap().nutrients(x, y))
+}
+
+/// Bring all the cells one tick closer to the base level of nutrients
+pub fn regenerate() {
+    SOIL.with_borrow_mut(|soil| soil.as_mut().unwrap().regenerate());
+}
+
+/// For renderer: center and nutrients of the cells away from the base level
+pub fn cells() -> Vec<((f64, f64), f64)> {
+    SOIL.with_borrow(|soil| soil.as_ref().unwrap().cells())
+}
+
+struct Soil {
+    config: SoilConfig,
+    cells: HashMap<(i64, i64), f64>,
+}
+
+impl Soil {
+    fn new(config: SoilConfig) -> Self {
+        Self {
+            config,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell_coords(&self, x: f64, y: f64) -> (i64, i64) {
+        (
+            (x / self.config.cell_size).floor() as i64,
+            (y / self.config.cell_size).floor() as i64,
+        )
+    }
+
+    fn cell_mut(&mut self, x: f64, y: f64) -> &mut f64 {
+        let base_nutrients = self.config.base_nutrients;
+        self.cells
+            .entry(self.cell_coords(x, y))
+            .or_insert(base_nutrients)
+    }
+
+    fn fertilize(&mut self, x: f64, y: f64, amount: f64) {
+        let max_nutrients = self.config.max_nutrients;
+        let cell = self.cell_mut(x, y);
+        *cell = (*cell + amount).min(max_nutrients);
+    }
+
+    fn consume(&mut self, x: f64, y: f64, amount: f64) -> f64 {
+        let cell = self.cell_mut(x, y);
+        let consumed = amount.min(*cell);
+        *cell -= consumed;
+        consumed
+    }
+
+    fn nutrients(&self, x: f64, y: f64) -> f64 {
+        self.cells
+            .get(&self.cell_coords(x, y))
+            .copied()
+            .unwrap_or(self.config.base_nutrients)
+    }
+
+    fn regenerate(&mut self) {
+        let (base, rate) = (self.config.base_nutrients, self.config.regeneration_rate);
+        for nutrients in self.cells.values_mut() {
+            *nutrients += (base - *nutrients).clamp(-rate, rate);
+        }
+        self.cells.retain(|_, nutrients| *nutrients != base);
+    }
+
+    fn cells(&self) -> Vec<((f64, f64), f64)> {
+        let size = self.config.cell_size;
+        self.cells
+            .iter()
+            .map(|(&(x, y), &nutrients)| {
+                (
+                    ((x as f64 + 0.5) * size, (y as f64 + 0.5) * size),
+                    nutrients,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{configuration::SoilConfig, shared_data::soil::Soil};
+
+    #[test]
+    fn test_soil() {
+        let mut soil = Soil::new(SoilConfig {
+            cell_size: 100.0,
+            base_nutrients: 2.0,
+            max_nutrients: 4.0,
+            regeneration_rate: 1.0,
+            nutrients_per_size: 1.0,
+            max_growth_factor: 2.0,
+            droppings_nutrients: 1.0,
+        });
+        soil.fertilize(10.0, 10.0, 1.0);
+        soil.fertilize(90.0, 50.0, 2.0);
+        assert_eq!(soil.consume(-10.0, 10.0, 3.0), 2.0);
+
+        // Same cell, capped nutrients
+        assert_eq!(soil.nutrients(50.0, 50.0), 4.0);
+        assert_eq!(soil.nutrients(-50.0, 50.0), 0.0);
+        assert_eq!(soil.nutrients(150.0, 50.0), 2.0);
+
+        // Back to the base level, from above and from below
+        soil.regenerate();
+        assert_eq!(soil.nutrients(50.0, 50.0), 3.0);
+        assert_eq!(soil.nutrients(-50.0, 50.0), 1.0);
+        soil.regenerate();
+        assert_eq!(soil.cells.len(), 0);
+    }
+}
@@ -1,7 +1,10 @@
 use crate::components::agent_component::AgentComponent;
+use crate::components::all::{CreatureComponent, MoveToTargetResultComponent, SleepingComponent};
+use crate::components::move_to_target_component::MoveToTargetComponent;
 use crate::configuration::Config;
-use crate::ecs::{Ecs, EntityInfo, System, iter_components, to_ctype};
-use crate::goap::{ActionResult, Goap, WorldState};
+use crate::ecs::{Ecs, EntityInfo, RESERVED_ENTITY_ID, System, iter_components, to_ctype};
+use crate::goap::{ActionResult, Goap, PlanningResult, WorldState};
+use crate::htn::{Agenda, Task};
 use std::any::TypeId;
 
 pub struct AgentSystem {
@@ -20,10 +23,83 @@ impl AgentSystem {
     pub fn goap(&self) -> &Goap {
         &self.goap
     }
+
+    /// Resume or start the plan searches of the agents that need one.
+    /// All agents share the same planning budget (number of nodes expanded per tick): the ones
+    /// that have waited the longest plan first, then the ones with the most urgent goal.
+    /// Agents that do not get any budget keep their search state and resume it on the next tick.
+    fn plan(&mut self, ecs: &mut Ecs, config: &Config) {
+        let mut planning_agents: Vec<(usize, usize, f32)> = Vec::new();
+        for (index, agent) in self.agents.iter().enumerate() {
+            if !agent.alive || agent.idle || agent.has_plan || agent.goal.is_none() {
+                continue;
+            }
+            let agent_component = ecs.component::<AgentComponent>(&agent.info).unwrap();
+            let (nb_ticks_waited, priority) = agent_component
+                .planner()
+                .map_or((0, 0.0), |p| (p.nb_ticks_waited(), p.priority()));
+            planning_agents.push((index, nb_ticks_waited, priority));
+        }
+        planning_agents.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.total_cmp(&a.2)));
+
+        let mut budget = config.agent.planning_budget_per_tick;
+        for (index, ..) in planning_agents {
+            let agent = &mut self.agents[index];
+            let agent_component = ecs.component_mut::<AgentComponent>(&agent.info).unwrap();
+
+            // No more budget: wait for the next tick
+            if budget == 0 {
+                if let Some(planner) = agent_component.planner_mut() {
+                    planner.wait();
+                }
+                continue;
+            }
+
+            let planner = agent_component.take_planner().or_else(|| {
+                self.goap
+                    .start_plan(&agent.world_state, agent.goal.unwrap(), agent.goal_set, 0.0)
+            });
+            let Some(mut planner) = planner else {
+                agent_component.reset_plan();
+                continue;
+            };
+
+            let (result, nb_expanded_nodes) = self.goap.continue_plan(
+                agent.info.entity,
+                &*agent_component,
+                &mut planner,
+                agent.goal_set,
+                agent.action_set,
+                budget,
+            );
+            budget -= nb_expanded_nodes.min(budget);
+
+            match result {
+                PlanningResult::Found(plan) if !plan.is_empty() => {
+                    agent.action = Some(plan[0]);
+                    agent.has_plan = true;
+                    agent_component.set_plan(plan);
+                }
+                PlanningResult::Found(_) => {
+                    // Goal already satisfied, go on with the next task of the agenda (if any)
+                    agent_component.reset_plan();
+                }
+                PlanningResult::Pending => {
+                    planner.wait();
+                    agent_component.set_planner(planner);
+                }
+                PlanningResult::NotFound => {
+                    // If no plan was found, look for a goal again on the next tick
+                    agent_component.abandon_agenda();
+                }
+            }
+        }
+    }
 }
 
 struct AgentInfo {
     info: EntityInfo,
+    alive: bool,
     has_plan: bool,
     goal: Option<usize>,
     goal_set: usize,
@@ -36,6 +112,7 @@ impl AgentInfo {
     fn new(agent: &AgentComponent, info: &EntityInfo) -> Self {
         AgentInfo {
             info: *info,
+            alive: true,
             has_plan: agent.has_plan(),
             goal: agent.goal,
             goal_set: agent.goal_set(),
@@ -45,10 +122,70 @@ impl AgentInfo {
             idle: agent.idle(),
         }
     }
+
+    /// Set the goal of the agent and start planning it
+    fn start_goal(&mut self, ecs: &mut Ecs, goap: &Goap, goal: usize, priority: f32) {
+        let planner = goap.start_plan(&self.world_state, goal, self.goal_set, priority);
+        let agent_component = ecs.component_mut::<AgentComponent>(&self.info).unwrap();
+        self.goal = Some(goal);
+        agent_component.goal = Some(goal);
+        if let Some(planner) = planner {
+            agent_component.set_planner(planner);
+        }
+    }
+
+    /// Abandon the current goal (and agenda), stopping the on-going move if any
+    fn interrupt(&mut self, ecs: &mut Ecs) {
+        let agent_component = ecs.component_mut::<AgentComponent>(&self.info).unwrap();
+        agent_component.abandon_agenda();
+        agent_component.target_entity = RESERVED_ENTITY_ID;
+
+        let mut updates = Vec::new();
+        if ecs.has_component(self.info.arch_index, &to_ctype!(MoveToTargetComponent)) {
+            Ecs::push_delete::<MoveToTargetComponent>(self.info, &mut updates);
+        }
+        if ecs.has_component(
+            self.info.arch_index,
+            &to_ctype!(MoveToTargetResultComponent),
+        ) {
+            Ecs::push_delete::<MoveToTargetResultComponent>(self.info, &mut updates);
+        }
+        // Wake up
+        if ecs.has_component(self.info.arch_index, &to_ctype!(SleepingComponent)) {
+            Ecs::push_delete::<SleepingComponent>(self.info, &mut updates);
+        }
+        ecs.apply(updates);
+        self.refresh(ecs);
+
+        self.goal = None;
+        self.has_plan = false;
+        self.action = None;
+    }
+
+    /// Check that the agent still exists (it may have been deleted by the other agents
+    /// performing their actions), and update its entity info
+    fn refresh(&mut self, ecs: &Ecs) -> bool {
+        let info_opt = ecs.get_entity_info(self.info.entity);
+        if let Some(info) = info_opt
+            && ecs.has_component(info.arch_index, &to_ctype!(AgentComponent))
+        {
+            self.info = info;
+        } else {
+            self.alive = false;
+        }
+        self.alive
+    }
+}
+
+fn creature_energy(ecs: &Ecs, info: &EntityInfo) -> f32 {
+    ecs.component::<CreatureComponent>(info)
+        .map_or(0.0, |creature| creature.energy)
 }
 
 impl System for AgentSystem {
     fn run(&mut self, ecs: &mut Ecs, config: &Config) {
+        self.goap.next_tick();
+
         // TODO This is just stupid, a recuring problem, I have to copy all relevant info.
         // I can't work directly in the loop because that would required borrowing ECS twice
         // (to pass a reference to the GOAP)
@@ -58,18 +195,8 @@ impl System for AgentSystem {
             .map(|(agent, info)| AgentInfo::new(agent, &info))
             .collect();
 
+        // Find a goal (or the next task of their agenda) for the agents that need one
         for agent in self.agents.iter_mut() {
-            // Check that the agent still exists (it may have been deleted by the other agents
-            // performing their actions)
-            let info_opt = ecs.get_entity_info(agent.info.entity);
-            if let Some(info) = info_opt
-                && ecs.has_component(info.arch_index, &to_ctype!(AgentComponent))
-            {
-                agent.info = info;
-            } else {
-                continue;
-            }
-
             // If the agent is in idle state, do nothing
             if agent.idle {
                 let agent_component = ecs.component_mut::<AgentComponent>(&agent.info).unwrap();
@@ -77,37 +204,87 @@ impl System for AgentSystem {
                 continue;
             }
 
-            // Find a goal if necessary
-            if agent.goal.is_none() {
-                if let Some(goal) = self.goap.find_goal(&*ecs, &agent.info, agent.goal_set) {
-                    let agent_component = ecs.component_mut::<AgentComponent>(&agent.info).unwrap();
-                    agent.goal = Some(goal);
-                    agent_component.goal = Some(goal);
-                } else {
-                    // If no goal was found, skip the agent
-                    continue;
-                }
+            // Interrupt the current goal if another one has become much more useful
+            if let Some(goal) = agent.goal
+                && self.goap.should_interrupt(
+                    &*ecs,
+                    &agent.info,
+                    agent.goal_set,
+                    goal,
+                    config.agent.goal_interruption_margin,
+                )
+            {
+                self.goap.record_interruption(agent.info.entity, goal);
+                agent.interrupt(ecs);
+            }
+            if agent.goal.is_some() || agent.has_plan {
+                continue;
             }
 
-            // Compute a plan if necessary
+            // Observe the world before choosing what to do next
+            self.goap.sense(
+                &*ecs,
+                &agent.info,
+                config,
+                agent.goal_set,
+                &mut agent.world_state,
+            );
+            let agent_component = ecs.component_mut::<AgentComponent>(&agent.info).unwrap();
+            agent_component.world_state = agent.world_state.clone();
+
+            let mut agenda = match agent_component.take_agenda() {
+                Some(agenda) if !agenda.is_empty() => agenda,
+                _ => {
+                    // If no goal is found, the agent is skipped
+                    let Some((goal, utility)) =
+                        self.goap
+                            .find_goal(&*ecs, &agent.info, agent.goal_set, &agent.world_state)
+                    else {
+                        continue;
+                    };
+                    match self.goap.goal_task(agent.goal_set, goal) {
+                        Some(task) => Agenda::new(Task::Compound(task), goal, utility),
+                        None => {
+                            agent.start_goal(ecs, &self.goap, goal, utility);
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            let task = self
+                .goap
+                .next_task(agent.info.entity, &mut agenda, &agent.world_state);
+            let (agenda_goal, priority) = (agenda.goal(), agenda.priority());
             let agent_component = ecs.component_mut::<AgentComponent>(&agent.info).unwrap();
-            if !agent.has_plan {
-                if let Some(plan) = self.goap.compute_plan(
-                    &*agent_component,
-                    &agent.world_state,
-                    agent.goal.unwrap(),
-                    agent.goal_set,
-                    agent.action_set,
-                ) && !plan.is_empty()
-                {
-                    agent.action = Some(plan[0]);
+            agent_component.set_agenda(agenda);
+            match task {
+                Some(Task::Goal(goal)) => agent.start_goal(ecs, &self.goap, goal, priority),
+                Some(Task::Action(action)) => {
+                    // The goal of the agenda can be interrupted while the action is performed
+                    agent.goal = Some(agenda_goal);
+                    agent_component.goal = Some(agenda_goal);
+                    agent.action = Some(action);
                     agent.has_plan = true;
-                    agent_component.set_plan(plan);
-                } else {
-                    // If no plan was found, skip the agent
-                    continue;
+                    agent_component.set_plan(vec![action]);
                 }
+                // Compound tasks are always decomposed, and an agenda that failed is empty
+                Some(Task::Compound(_)) | None => {}
             }
+        }
+
+        // Compute plans, within the planning budget
+        self.plan(ecs, config);
+
+        for agent in self.agents.iter_mut() {
+            if !agent.has_plan || agent.idle || !agent.refresh(ecs) {
+                continue;
+            }
+
+            // Measure the resources spent by the action, to learn its cost
+            let energy = creature_energy(ecs, &agent.info);
+            let agent_component = ecs.component_mut::<AgentComponent>(&agent.info).unwrap();
+            agent_component.tick_action(energy);
 
             // Perform the current action, and if its completed advance to next action in the plan
             let result = self.goap.perform_action(
@@ -120,24 +297,30 @@ impl System for AgentSystem {
             );
 
             // After performing the action, check that the agent still exists
-            let info_opt = ecs.get_entity_info(agent.info.entity);
-            if let Some(info) = info_opt
-                && ecs.has_component(info.arch_index, &to_ctype!(AgentComponent))
-            {
-                agent.info = info;
-            } else {
+            if !agent.refresh(ecs) {
                 continue;
             }
 
+            let energy = creature_energy(ecs, &agent.info);
             let agent_component = ecs.component_mut::<AgentComponent>(&agent.info).unwrap();
             match result {
                 ActionResult::Success => {
+                    agent_component.record_action_outcome(
+                        agent.action.unwrap(),
+                        true,
+                        energy,
+                        &config.agent,
+                    );
                     agent_component.next_action();
-                    agent_component.reset_action_cost(agent.action.unwrap());
                 }
                 ActionResult::Failure => {
-                    agent_component.reset_plan();
-                    agent_component.increase_action_cost(agent.action.unwrap());
+                    agent_component.record_action_outcome(
+                        agent.action.unwrap(),
+                        false,
+                        energy,
+                        &config.agent,
+                    );
+                    agent_component.abandon_agenda();
                 }
                 ActionResult::OnGoing => {
                     agent_component.world_state = agent.world_state.clone();
@@ -146,3 +329,215 @@ impl System for AgentSystem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        components::agent_component::AgentComponent,
+        configuration::{Config, test_config},
+        decision_trace::DecisionEvent,
+        ecs::{Ecs, EntityInfo, System, Update, iter_components},
+        goap::{
+            Action, ActionResult, ActionSet, Condition, Effect, Goal, GoalSet, Goap, Modifier,
+            Operator, Symbol, Value, WorldState,
+        },
+        htn::{CompoundTask, Task},
+        systems::agent_system::{AgentInfo, AgentSystem},
+    };
+    use std::any::TypeId;
+
+    struct NearPlantGoal {
+        conditions: [Condition; 1],
+    }
+    impl Goal for NearPlantGoal {
+        fn conditions(&self) -> &[Condition] {
+            &self.conditions
+        }
+        fn utility(&self, _ecs: &Ecs, _info: &EntityInfo) -> f32 {
+            1.0
+        }
+    }
+
+    struct MoveAction {
+        preconditions: Vec<Condition>,
+        effects: [Effect; 1],
+    }
+    impl Action for MoveAction {
+        fn preconditions(&self) -> &[Condition] {
+            &self.preconditions
+        }
+        fn effects(&self) -> &[Effect] {
+            &self.effects
+        }
+        fn perform(
+            &self,
+            _ecs: &mut Ecs,
+            _info: &EntityInfo,
+            _config: &Config,
+        ) -> Result<ActionResult, String> {
+            Ok(ActionResult::Success)
+        }
+        fn description(&self) -> String {
+            String::new()
+        }
+    }
+
+    fn near_plant_goal() -> Box<NearPlantGoal> {
+        Box::new(NearPlantGoal {
+            conditions: [Condition::new(
+                Symbol::IsNearPlant,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+        })
+    }
+
+    // Move near a corpse, then from the corpse to a plant
+    fn move_actions() -> ActionSet {
+        let mut act_s = ActionSet::new();
+        act_s.add(Box::new(MoveAction {
+            preconditions: Vec::new(),
+            effects: [Effect::new(
+                Symbol::IsNearCorpse,
+                Modifier::SetValue,
+                Value::Bool(true),
+            )],
+        }));
+        act_s.add(Box::new(MoveAction {
+            preconditions: vec![Condition::new(
+                Symbol::IsNearCorpse,
+                Operator::Equal,
+                Value::Bool(true),
+            )],
+            effects: [Effect::new(
+                Symbol::IsNearPlant,
+                Modifier::SetValue,
+                Value::Bool(true),
+            )],
+        }));
+        act_s
+    }
+
+    fn planner_state(ecs: &Ecs, entity: usize) -> Option<(usize, usize)> {
+        let info = ecs.get_entity_info(entity).unwrap();
+        ecs.component::<AgentComponent>(&info)
+            .unwrap()
+            .planner()
+            .map(|p| (p.nb_ticks_waited(), p.nb_expanded_nodes()))
+    }
+
+    fn has_plan(ecs: &Ecs, entity: usize) -> bool {
+        let info = ecs.get_entity_info(entity).unwrap();
+        ecs.component::<AgentComponent>(&info).unwrap().has_plan()
+    }
+
+    #[test]
+    fn test_planning_budget() {
+        let mut config = test_config();
+        config.agent.planning_budget_per_tick = 2;
+
+        // A plan of two actions, that takes three ticks to find with this budget
+        let mut goap = Goap::new();
+        let mut gs = GoalSet::new();
+        gs.add(near_plant_goal());
+        let goal_set = goap.add_goal_set(gs);
+        let action_set = goap.add_action_set(move_actions());
+
+        // The first agent has the most urgent goal, the second one has waited the longest
+        let mut ecs = Ecs::new();
+        for (priority, nb_ticks_waited) in [(10.0, 0), (1.0, 3)] {
+            let mut agent = AgentComponent::new(goal_set, action_set, 2);
+            let mut planner = goap
+                .start_plan(&WorldState::new(), 0, goal_set, priority)
+                .unwrap();
+            for _ in 0..nb_ticks_waited {
+                planner.wait();
+            }
+            agent.goal = Some(0);
+            agent.set_planner(planner);
+            ecs.apply(vec![Update::Create(vec![Box::new(agent)])]);
+        }
+        let entities: Vec<usize> = iter_components!(ecs, (), (AgentComponent))
+            .map(|(_, info)| info.entity)
+            .collect();
+        let (urgent, waiting) = (entities[0], entities[1]);
+
+        let mut system = AgentSystem::new(goap);
+        let mut tick = |ecs: &mut Ecs| {
+            system.agents = iter_components!(ecs, (), (AgentComponent))
+                .map(|(agent, info)| AgentInfo::new(agent, &info))
+                .collect();
+            system.plan(ecs, &config);
+        };
+
+        // The agent that waited the longest uses all the budget, the other one keeps its search
+        // state and waits
+        tick(&mut ecs);
+        assert_eq!(planner_state(&ecs, waiting), Some((4, 2)));
+        assert_eq!(planner_state(&ecs, urgent), Some((1, 0)));
+
+        // The first one still has waited the longest: it finds its plan, then the other one
+        // resumes its search
+        tick(&mut ecs);
+        assert!(has_plan(&ecs, waiting));
+        assert_eq!(planner_state(&ecs, urgent), Some((2, 2)));
+
+        tick(&mut ecs);
+        assert!(has_plan(&ecs, urgent));
+    }
+
+    #[test]
+    fn test_agenda() {
+        let config = test_config();
+
+        // The first move is performed without planning, then the goal is planned
+        let mut goap = Goap::new();
+        goap.enable_decision_trace(10, 10);
+        let mut approach = CompoundTask::new("approach");
+        approach.add_method(vec![], vec![Task::Action(0), Task::Goal(0)]);
+        let approach = goap.add_compound_task(approach);
+        let mut gs = GoalSet::new();
+        gs.add_with_task(near_plant_goal(), approach);
+        let goal_set = goap.add_goal_set(gs);
+        let action_set = goap.add_action_set(move_actions());
+
+        let mut ecs = Ecs::new();
+        ecs.apply(vec![Update::Create(vec![Box::new(AgentComponent::new(
+            goal_set, action_set, 2,
+        ))])]);
+        let entity = iter_components!(ecs, (), (AgentComponent))
+            .map(|(_, info)| info.entity)
+            .next()
+            .unwrap();
+        let agent = |ecs: &Ecs| {
+            let info = ecs.get_entity_info(entity).unwrap();
+            let agent = ecs.component::<AgentComponent>(&info).unwrap();
+            let tasks: Vec<Task> = agent.agenda().unwrap().tasks().copied().collect();
+            (agent.goal, tasks)
+        };
+
+        let mut system = AgentSystem::new(goap);
+        system.run(&mut ecs, &config);
+        assert_eq!(agent(&ecs), (None, vec![Task::Goal(0)]));
+        let events: Vec<&DecisionEvent> = system
+            .goap()
+            .agent_decisions(entity)
+            .map(|r| &r.event)
+            .collect();
+        assert!(matches!(
+            events[..],
+            [
+                DecisionEvent::GoalChosen { goal: 0, .. },
+                DecisionEvent::TaskDecomposed { method: 0, .. },
+                DecisionEvent::ActionPerformed {
+                    action: 0,
+                    success: true,
+                    ..
+                },
+            ]
+        ));
+
+        system.run(&mut ecs, &config);
+        assert_eq!(agent(&ecs), (Some(0), vec![]));
+    }
+}
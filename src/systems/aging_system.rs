@@ -0,0 +1,40 @@
+use crate::components::age_component::{AgeComponent, LifeStage};
+use crate::components::all::CreatureComponent;
+use crate::components::body_component::BodyComponent;
+use crate::components::genome_component::GenomeComponent;
+use crate::configuration::Config;
+use crate::ecs::{Ecs, System, iter_components};
+use std::any::TypeId;
+
+/* Creatures get older every tick: juveniles grow up to the size given by their genome, and
+ * creatures reaching the end of their lifespan die (their corpse is created by the death system).
+ */
+pub struct AgingSystem;
+impl System for AgingSystem {
+    fn run(&mut self, ecs: &mut Ecs, config: &Config) {
+        for (age, creature, body, genome, info) in iter_components!(
+            ecs,
+            (),
+            (
+                AgeComponent,
+                CreatureComponent,
+                BodyComponent,
+                GenomeComponent
+            )
+        ) {
+            if !age.grow_older(&config.aging) {
+                // Natural death
+                creature.health = 0.0;
+                continue;
+            }
+
+            // Growing (retried on the next tick if there is no room for it)
+            if age.stage == LifeStage::Juvenile || body.w() < genome.size {
+                let size = genome.size * age.size_factor(&config.aging);
+                if size != body.w() {
+                    body.try_update_size(info.entity, size, size);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::components::all::CorpseComponent;
+use crate::components::body_component::BodyComponent;
+use crate::configuration::Config;
+use crate::ecs::{Ecs, System, Update, iter_components};
+use crate::shared_data::soil;
+use std::any::TypeId;
+
+/* Corpses decay, losing their energy to the soil around them as nutrients for the plants,
+ * until they vanish.
+ */
+pub struct CorpseDecaySystem;
+impl System for CorpseDecaySystem {
+    fn run(&mut self, ecs: &mut Ecs, config: &Config) {
+        let mut updates: Vec<Update> = Vec::new();
+        for (corpse, body, info) in iter_components!(ecs, (), (CorpseComponent, BodyComponent)) {
+            let decayed = corpse.energy.min(config.corpse.decay_rate);
+            corpse.energy -= decayed;
+            soil::fertilize(
+                body.x(),
+                body.y(),
+                decayed as f64 * config.corpse.nutrients_per_energy,
+            );
+
+            if corpse.energy <= 0.0 {
+                updates.push(Update::DeleteEntity(info));
+            }
+        }
+        ecs.apply(updates);
+    }
+}
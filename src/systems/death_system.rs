@@ -2,11 +2,12 @@ use crate::components::all::*;
 use crate::components::body_component::BodyComponent;
 use crate::configuration::Config;
 use crate::ecs::{Ecs, System, Update, iter_entities};
+use log::info;
 use std::any::TypeId;
 
 pub struct DeathSystem;
 impl System for DeathSystem {
-    fn run(&mut self, ecs: &mut Ecs, _config: &Config) {
+    fn run(&mut self, ecs: &mut Ecs, config: &Config) {
         let mut updates: Vec<Update> = Vec::new();
 
         for info in iter_entities!(ecs, CreatureComponent, BodyComponent) {
@@ -14,10 +15,19 @@ impl System for DeathSystem {
             if let Some(creature) = ecs.component::<CreatureComponent>(&info)
                 && creature.health <= 0.0
             {
-                // Create a corpse
+                // The creature was killed if an attack brought its health to zero
+                if let Some(killer) = creature.killer {
+                    info!("Creature {} killed by creature {}", info.entity, killer);
+                }
+
+                // Create a corpse, as nourishing as the creature was big and well fed
                 if let Some(body) = ecs.component::<BodyComponent>(&info) {
                     updates.push(Update::Create(vec![
-                        Box::new(CorpseComponent),
+                        Box::new(CorpseComponent::of(
+                            info.entity,
+                            creature.killer,
+                            config.corpse_energy(body.w(), creature.energy),
+                        )),
                         Box::new(BodyComponent::new_not_traversable(
                             body.x(),
                             body.y(),
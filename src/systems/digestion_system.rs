@@ -1,9 +1,11 @@
 use crate::TypeId;
-use crate::components::all::{HerbivorousComponent, PlantComponent};
+use crate::components::all::{DigestionComponent, PlantComponent};
 use crate::components::body_component::BodyComponent;
 use crate::configuration::Config;
 use crate::ecs::{Ecs, RESERVED_ENTITY_ID, System, Update, iter_components};
+use crate::shared_data::biome;
 use crate::shared_data::body_grid;
+use crate::shared_data::soil;
 use std::f64::consts::PI;
 
 pub struct DigestionSystem;
@@ -11,14 +13,12 @@ impl System for DigestionSystem {
     fn run(&mut self, ecs: &mut Ecs, config: &Config) {
         let mut updates: Vec<Update> = Vec::new();
 
-        for (herbivorous, body, _) in
-            iter_components!(ecs, (), (HerbivorousComponent, BodyComponent))
-        {
+        for (digestion, body, _) in iter_components!(ecs, (), (DigestionComponent, BodyComponent)) {
             // Get the seeds closest to being excreted
-            if herbivorous.seeds.is_empty() {
+            if digestion.seeds.is_empty() {
                 continue;
             }
-            let (nb_seeds, ref mut coutdown_to_excretion) = herbivorous.seeds[0];
+            let (nb_seeds, ref mut coutdown_to_excretion) = digestion.seeds[0];
 
             // Check if seeds ready to be excreted
             if *coutdown_to_excretion > 0 {
@@ -26,10 +26,10 @@ impl System for DigestionSystem {
                 continue;
             }
 
-            // Create plants by shitting the seeds in a circle around the herbivorous
+            // Create plants by shitting the seeds in a circle around the creature
             let arc = 2.0 * PI / (nb_seeds as f64);
             let mut a: f64 = 0.0;
-            let body_size = body.w(); // Assume the herbivorous is squared
+            let body_size = body.w(); // Assume the creature is squared
             for _ in 0..nb_seeds {
                 let x = a.cos() * body_size;
                 let y = a.sin() * body_size;
@@ -43,7 +43,10 @@ impl System for DigestionSystem {
                     config.seed.size,
                 );
 
-                if !body_grid::collides(RESERVED_ENTITY_ID, &seed_body) {
+                // Seeds falling into water are lost
+                if !biome::is_water(seed_body.x(), seed_body.y())
+                    && !body_grid::collides(RESERVED_ENTITY_ID, &seed_body)
+                {
                     updates.push(Update::Create(vec![
                         Box::new(seed_body),
                         Box::new(PlantComponent::new(config)),
@@ -51,7 +54,10 @@ impl System for DigestionSystem {
                 }
             }
 
-            herbivorous.seeds.pop_front();
+            // Droppings enrich the soil
+            soil::fertilize(body.x(), body.y(), config.soil.droppings_nutrients);
+
+            digestion.seeds.pop_front();
         }
 
         ecs.apply(updates);
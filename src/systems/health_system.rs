@@ -7,12 +7,22 @@ pub struct HealthSystem;
 impl System for HealthSystem {
     fn run(&mut self, ecs: &mut Ecs, config: &Config) {
         for (creature, _) in iter_components!(ecs, (), (CreatureComponent)) {
-            creature.health += if creature.energy > 0.0 {
-                config.creature.recovery_rate
-            } else {
-                -config.creature.exhaustion_rate
-            };
+            let starving = creature.energy <= 0.0;
+            let dehydrated = creature.thirst >= config.creature.max_thirst;
+            if starving {
+                creature.health -= config.creature.exhaustion_rate;
+            }
+            if dehydrated {
+                creature.health -= config.creature.dehydration_rate;
+            }
+            if !starving && !dehydrated {
+                creature.health += config.creature.recovery_rate;
+            }
             creature.health = creature.health.clamp(0.0, config.creature.max_health);
+            // Healed
+            if creature.health >= config.creature.max_health {
+                creature.last_attacker = None;
+            }
         }
     }
 }
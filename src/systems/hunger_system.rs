@@ -1,16 +1,23 @@
 use crate::components::all::*;
+use crate::components::genome_component::GenomeComponent;
 use crate::configuration::Config;
 use crate::ecs::{Ecs, System, iter_components};
+use crate::shared_data::climate;
 use std::any::TypeId;
 
 pub struct HungerSystem;
 impl System for HungerSystem {
     fn run(&mut self, ecs: &mut Ecs, config: &Config) {
-        for (creature, _) in iter_components!(ecs, (), (CreatureComponent)) {
-            creature.energy -= config.creature.hunger_rate;
+        // Creatures get hungrier in the cold seasons
+        let hunger_factor = climate::current().hunger_factor();
+        for (creature, genome, _) in iter_components!(ecs, (), (CreatureComponent, GenomeComponent))
+        {
+            creature.energy -= genome.hunger_rate * hunger_factor;
             if creature.energy <= 0.0 {
                 creature.energy = 0.0;
             }
+            creature.thirst =
+                (creature.thirst + config.creature.thirst_rate).min(config.creature.max_thirst);
         }
     }
 }
@@ -0,0 +1,318 @@
+use crate::components::all::{CorpseComponent, PlantComponent, WaterComponent};
+use crate::components::body_component::BodyComponent;
+use crate::components::inbox_component::{InboxComponent, Signal};
+use crate::components::memory_component::{MemoryComponent, MemoryKind};
+use crate::configuration::Config;
+use crate::ecs::{Ecs, EntityId, EntityInfo, System, iter_entities};
+use crate::systems::{message_system, utils};
+use std::any::TypeId;
+
+/* Creatures remember the food sources, prey and predators they see, and the ones they are told
+ * about (as well as the creatures calling for a mate). They warn the others about the predators
+ * they see.
+ * Memories of entities that should be seen at their remembered location, but are not, are
+ * forgotten (the food has been eaten, the creature has moved). The others (out of sight) decay
+ * over time.
+ */
+pub struct MemorySystem {
+    tick: usize,
+}
+impl MemorySystem {
+    pub fn new() -> Self {
+        Self { tick: 0 }
+    }
+}
+impl System for MemorySystem {
+    fn run(&mut self, ecs: &mut Ecs, config: &Config) {
+        self.tick += 1;
+        let period = config.memory.perception_period_ticks.max(1);
+        if !self.tick.is_multiple_of(period) {
+            return;
+        }
+
+        let infos: Vec<EntityInfo> = iter_entities!(ecs, MemoryComponent, BodyComponent).collect();
+        for info in infos {
+            let body = *ecs.component::<BodyComponent>(&info).unwrap();
+            let distance = utils::perception_distance(ecs, &info, &config.creature);
+            let seen = perceive(ecs, &info, &body, config);
+            let heard = listen(ecs, &info, period);
+
+            // Alarm calls
+            for (_, predator, position) in seen
+                .iter()
+                .filter(|(kind, _, _)| *kind == MemoryKind::Predator)
+            {
+                message_system::broadcast(
+                    ecs,
+                    &info,
+                    &body,
+                    Signal::Alarm,
+                    *predator,
+                    *position,
+                    config,
+                );
+            }
+
+            // Remembered locations in sight, where the entity is not found anymore (if it is seen
+            // elsewhere, it is remembered again at its new position)
+            let unseen: Vec<EntityId> = ecs
+                .component::<MemoryComponent>(&info)
+                .unwrap()
+                .entries()
+                .iter()
+                .filter(|e| {
+                    (e.x - body.x()).powi(2) + (e.y - body.y()).powi(2) < distance.powi(2)
+                        && !seen.iter().any(|(_, entity, position)| {
+                            *entity == e.entity && *position == (e.x, e.y)
+                        })
+                        && utils::is_visible(
+                            ecs,
+                            &info,
+                            &body,
+                            e.entity,
+                            &BodyComponent::new_traversable(e.x, e.y, 0.0, 0.0),
+                        )
+                })
+                .map(|e| e.entity)
+                .collect();
+
+            let memory = ecs.component_mut::<MemoryComponent>(&info).unwrap();
+            memory.decay(period, &config.memory);
+            for entity in unseen {
+                memory.forget(entity);
+            }
+            for (kind, entity, position) in heard.into_iter().chain(seen) {
+                memory.remember(kind, entity, position, &config.memory);
+            }
+        }
+    }
+}
+
+/// Return the eatable plants, corpses, water sources, prey and predators seen by the creature
+fn perceive(
+    ecs: &Ecs,
+    info: &EntityInfo,
+    body: &BodyComponent,
+    config: &Config,
+) -> Vec<(MemoryKind, EntityId, (f64, f64))> {
+    let plants = utils::find_visible::<PlantComponent>(ecs, info, body, &config.creature)
+        .into_iter()
+        .filter(|(_, entity, _)| {
+            ecs.component_from_entity::<PlantComponent>(*entity)
+                .is_some_and(|plant| plant.is_eatable())
+        })
+        .map(|(_, entity, b)| (MemoryKind::Plant, entity, (b.x(), b.y())));
+    let corpses = utils::find_visible::<CorpseComponent>(ecs, info, body, &config.creature)
+        .into_iter()
+        .map(|(_, entity, b)| (MemoryKind::Corpse, entity, (b.x(), b.y())));
+    let prey = utils::find_visible_species(
+        ecs,
+        info,
+        body,
+        &utils::prey_of(ecs, info, config),
+        &config.creature,
+    )
+    .into_iter()
+    .map(|(_, entity, b)| (MemoryKind::Prey, entity, (b.x(), b.y())));
+    let water = utils::find_visible::<WaterComponent>(ecs, info, body, &config.creature)
+        .into_iter()
+        .map(|(_, entity, b)| (MemoryKind::Water, entity, (b.x(), b.y())));
+    let predators = utils::find_visible_species(
+        ecs,
+        info,
+        body,
+        &utils::predators_of(ecs, info, config),
+        &config.creature,
+    )
+    .into_iter()
+    .map(|(_, entity, b)| (MemoryKind::Predator, entity, (b.x(), b.y())));
+    plants
+        .chain(corpses)
+        .chain(water)
+        .chain(prey)
+        .chain(predators)
+        .collect()
+}
+
+/// Return what the creature has been told about since the last update of its memory
+fn listen(ecs: &Ecs, info: &EntityInfo, period: usize) -> Vec<(MemoryKind, EntityId, (f64, f64))> {
+    ecs.component::<InboxComponent>(info)
+        .into_iter()
+        .flat_map(|inbox| inbox.recent_messages(period))
+        .map(|m| {
+            let kind = match m.signal {
+                Signal::Alarm => MemoryKind::Predator,
+                Signal::FoodFound(food) => food.memory_kind(),
+                Signal::MatingCall => MemoryKind::Mate,
+            };
+            (kind, m.subject, (m.x, m.y))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        components::{
+            all::SpeciesComponent,
+            body_component::BodyComponent,
+            inbox_component::{InboxComponent, Signal},
+            memory_component::{MemoryComponent, MemoryEntry, MemoryKind},
+        },
+        configuration::test_config,
+        ecs::{Ecs, EntityId, EntityInfo, System, Update, iter_components, iter_entities},
+        shared_data::{biome, body_grid, climate},
+        systems::memory_system::MemorySystem,
+    };
+    use std::any::TypeId;
+
+    #[test]
+    fn test_prey_memory() {
+        let config = test_config();
+        body_grid::init(&config);
+        climate::init(&config);
+        biome::init_without_water(&config);
+        let (hunter_species, prey_species) = (1, 0);
+        assert!(config.is_prey_of(prey_species, hunter_species));
+        let distance = config.creature.perception_radius;
+        let (x, y) = (0.0, 0.0);
+
+        let mut ecs = Ecs::new();
+        ecs.apply(vec![Update::Create(vec![
+            Box::new(BodyComponent::new_not_traversable(x, y, 1.0, 1.0)),
+            Box::new(SpeciesComponent::new(hunter_species)),
+            Box::new(MemoryComponent::new()),
+        ])]);
+        ecs.apply(vec![Update::Create(vec![
+            Box::new(BodyComponent::new_not_traversable(
+                x + distance / 2.0,
+                y,
+                1.0,
+                1.0,
+            )),
+            Box::new(SpeciesComponent::new(prey_species)),
+        ])]);
+        let hunter = iter_components!(ecs, (), (MemoryComponent))
+            .map(|(_, info)| info.entity)
+            .next()
+            .unwrap();
+        let prey = iter_components!(ecs, (), (SpeciesComponent))
+            .map(|(_, info)| info.entity)
+            .find(|entity| *entity != hunter)
+            .unwrap();
+
+        let mut system = MemorySystem::new();
+        let mut perceive = |ecs: &mut Ecs| {
+            for _ in 0..config.memory.perception_period_ticks {
+                system.run(ecs, &config);
+            }
+            let info = ecs.get_entity_info(hunter).unwrap();
+            ecs.component::<MemoryComponent>(&info)
+                .unwrap()
+                .recall(MemoryKind::Prey, (x, y))
+        };
+        let move_prey = |ecs: &mut Ecs, prey_x: f64| {
+            let info = ecs.get_entity_info(prey).unwrap();
+            let body = ecs.component_mut::<BodyComponent>(&info).unwrap();
+            let offset_x = prey_x - body.x();
+            assert!(body.try_translate(prey, offset_x, 0.0));
+            body_grid::purge_deleted_bodies();
+        };
+        let remembered_at = |entries: Vec<MemoryEntry>| -> Vec<(EntityId, f64)> {
+            entries.iter().map(|e| (e.entity, e.x)).collect()
+        };
+
+        // The prey seen is remembered, and remembered again where it is seen next
+        assert_eq!(
+            remembered_at(perceive(&mut ecs)),
+            [(prey, x + distance / 2.0)]
+        );
+        move_prey(&mut ecs, x - distance / 2.0);
+        assert_eq!(
+            remembered_at(perceive(&mut ecs)),
+            [(prey, x - distance / 2.0)]
+        );
+
+        // Out of sight, it is forgotten as it is not found at its remembered position
+        move_prey(&mut ecs, x + distance * 1.5);
+        assert!(perceive(&mut ecs).is_empty());
+    }
+
+    #[test]
+    fn test_alarm() {
+        let config = test_config();
+        body_grid::init(&config);
+        climate::init(&config);
+        let (prey_species, predator_species) = (0, 1);
+        assert!(config.is_prey_of(prey_species, predator_species));
+        let distance = config.creature.perception_radius;
+        let alarm_range = config.messaging.alarm_range;
+
+        // A lookout seeing a predator, a member of its species out of sight of the predator, and
+        // another predator
+        let mut ecs = Ecs::new();
+        ecs.apply(vec![
+            Update::Create(vec![
+                Box::new(BodyComponent::new_not_traversable(0.0, 0.0, 1.0, 1.0)),
+                Box::new(SpeciesComponent::new(prey_species)),
+                Box::new(MemoryComponent::new()),
+            ]),
+            Update::Create(vec![
+                Box::new(BodyComponent::new_not_traversable(
+                    distance / 2.0,
+                    0.0,
+                    1.0,
+                    1.0,
+                )),
+                Box::new(SpeciesComponent::new(predator_species)),
+            ]),
+            Update::Create(vec![
+                Box::new(BodyComponent::new_not_traversable(
+                    -alarm_range / 2.0,
+                    0.0,
+                    1.0,
+                    1.0,
+                )),
+                Box::new(SpeciesComponent::new(prey_species)),
+                Box::new(InboxComponent::new()),
+            ]),
+            Update::Create(vec![
+                Box::new(BodyComponent::new_not_traversable(
+                    0.0,
+                    alarm_range / 2.0,
+                    1.0,
+                    1.0,
+                )),
+                Box::new(SpeciesComponent::new(predator_species)),
+                Box::new(InboxComponent::new()),
+            ]),
+        ]);
+        let mut system = MemorySystem::new();
+        for _ in 0..config.memory.perception_period_ticks {
+            system.run(&mut ecs, &config);
+        }
+
+        // Only the creatures of the species of the lookout are warned
+        let species_of =
+            |info: &EntityInfo| ecs.component::<SpeciesComponent>(info).unwrap().species;
+        let predator = iter_entities!(ecs, SpeciesComponent)
+            .find(|info| {
+                species_of(info) == predator_species
+                    && ecs.component::<InboxComponent>(info).is_none()
+            })
+            .unwrap();
+        for info in iter_entities!(ecs, InboxComponent) {
+            let alarms: Vec<(EntityId, f64)> = ecs
+                .component::<InboxComponent>(&info)
+                .unwrap()
+                .messages(Signal::Alarm)
+                .map(|m| (m.subject, m.x))
+                .collect();
+            if species_of(&info) == prey_species {
+                assert_eq!(alarms, [(predator.entity, distance / 2.0)]);
+            } else {
+                assert!(alarms.is_empty());
+            }
+        }
+    }
+}
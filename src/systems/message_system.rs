@@ -0,0 +1,60 @@
+use crate::components::all::SpeciesComponent;
+use crate::components::body_component::BodyComponent;
+use crate::components::inbox_component::{InboxComponent, Message, Signal};
+use crate::configuration::Config;
+use crate::ecs::{Ecs, EntityId, EntityInfo, System, iter_components};
+use crate::systems::utils;
+use std::any::TypeId;
+
+/// Messages are only kept for a short time by their receivers
+pub struct MessageSystem;
+impl System for MessageSystem {
+    fn run(&mut self, ecs: &mut Ecs, config: &Config) {
+        for (inbox, _) in iter_components!(ecs, (), (InboxComponent)) {
+            inbox.decay(config.messaging.ttl_ticks);
+        }
+    }
+}
+
+/// Send a signal about the subject (at the given position) to the creatures of the same species
+/// within the range of the signal
+pub fn broadcast(
+    ecs: &mut Ecs,
+    info: &EntityInfo,
+    body: &BodyComponent,
+    signal: Signal,
+    subject: EntityId,
+    (x, y): (f64, f64),
+    config: &Config,
+) {
+    let Some(species) = ecs.component::<SpeciesComponent>(info).copied() else {
+        return;
+    };
+    let range = match signal {
+        Signal::Alarm => config.messaging.alarm_range,
+        Signal::FoodFound(_) => config.messaging.food_found_range,
+        Signal::MatingCall => config.messaging.mating_call_range,
+    };
+    let receivers: Vec<EntityId> =
+        utils::find_all_in_range::<InboxComponent>(ecs, info.entity, body, range)
+            .into_iter()
+            .filter(|(_, entity, _)| {
+                ecs.component_from_entity::<SpeciesComponent>(*entity) == Some(&species)
+            })
+            .map(|(_, entity, _)| entity)
+            .collect();
+
+    let message = Message {
+        signal,
+        sender: info.entity,
+        subject,
+        x,
+        y,
+        age: 0,
+    };
+    for receiver in receivers {
+        if let Some(inbox) = ecs.component_mut_from_entity::<InboxComponent>(receiver) {
+            inbox.receive(message);
+        }
+    }
+}
@@ -1,8 +1,12 @@
 pub mod agent_system;
+pub mod aging_system;
+pub mod corpse_decay_system;
 pub mod death_system;
 pub mod digestion_system;
 pub mod health_system;
 pub mod hunger_system;
+pub mod memory_system;
+pub mod message_system;
 pub mod move_to_target_system;
 pub mod plant_growth_system;
 pub mod reproduction_system;
@@ -1,8 +1,16 @@
-use crate::components::all::MoveToTargetResultComponent;
+use crate::components::all::{
+    CreatureComponent, HerdComponent, MoveToTargetResultComponent, PerceptionComponent,
+    SpeciesComponent,
+};
 use crate::components::body_component::BodyComponent;
+use crate::components::genome_component::GenomeComponent;
 use crate::components::move_to_target_component::MoveToTargetComponent;
-use crate::configuration::Config;
-use crate::ecs::{Ecs, EntityId, EntityInfo, System, Update, iter_components};
+use crate::configuration::{Config, HerdConfig};
+use crate::ecs::{
+    Ecs, EntityId, EntityInfo, RESERVED_ENTITY_ID, System, Update, iter_components, iter_entities,
+};
+use crate::shared_data::{biome, body_grid};
+use crate::systems::utils;
 use std::any::TypeId;
 use std::collections::HashMap;
 
@@ -15,6 +23,9 @@ enum MoveToTargetResult {
 /* Move all entities towards their target while avoiding collisions.
  * Each entity follows a path composed of a series of waypoint (computed to avoid collisions).
  * If a collision occurs (i.e because other entities moved), a new path is computed.
+ * Members of a herd are also steered by their neighbours (separation, alignment and cohesion).
+ * Moving targets can be chased: the pursuer heads for the point where it can intercept the target.
+ * The speed depends on the biome crossed, and water cannot be entered.
  */
 pub struct MoveToTargetSystem;
 impl System for MoveToTargetSystem {
@@ -30,11 +41,60 @@ impl System for MoveToTargetSystem {
             *body = ecs.component_from_entity::<BodyComponent>(*entity).copied();
         }
 
+        // Creatures move at the speed given by their genome and their age
+        let speeds: HashMap<EntityId, f64> =
+            iter_entities!(ecs, MoveToTargetComponent, GenomeComponent)
+                .map(|info| (info.entity, utils::creature_speed(ecs, &info, config)))
+                .collect();
+
+        // Positions and headings of the herd members, by entity
+        let herd_members: HashMap<EntityId, HerdMember> =
+            iter_components!(ecs, (), (BodyComponent, SpeciesComponent, HerdComponent))
+                .map(|(body, species, herd, info)| {
+                    (
+                        info.entity,
+                        HerdMember {
+                            species: species.species,
+                            position: (body.x(), body.y()),
+                            heading: herd.heading,
+                        },
+                    )
+                })
+                .collect();
+
         // Iterate over all "move to target" entities
+        let mut offsets_moved: Vec<(EntityInfo, f64, f64)> = Vec::new();
         for (body, move_to_target, info) in
             iter_components!(ecs, (), (BodyComponent, MoveToTargetComponent))
         {
-            match try_move(config, body, move_to_target, &info, &target_bodies) {
+            let (x, y) = (body.x(), body.y());
+            // Slowed down by the terrain
+            let speed = speeds
+                .get(&info.entity)
+                .copied()
+                .unwrap_or(move_to_target.speed())
+                / biome::movement_cost(x, y).unwrap_or(1.0);
+            let steering = herd_members.get(&info.entity).and_then(|member| {
+                let herd_config = config.species[member.species].herd?;
+                let neighbours: Vec<HerdMember> =
+                    body_grid::iter_closest(info.entity, body, herd_config.radius)
+                        .filter_map(|(entity, _)| herd_members.get(&entity).copied())
+                        .filter(|neighbour| neighbour.species == member.species)
+                        .collect();
+                Some(herd_steering(member.position, &neighbours, &herd_config))
+            });
+            let result = try_move(
+                config,
+                body,
+                move_to_target,
+                speed,
+                steering,
+                &info,
+                &target_bodies,
+            );
+            offsets_moved.push((info, body.x() - x, body.y() - y));
+
+            match result {
                 MoveToTargetResult::Stopped => {
                     // Go into motionless state
                     Ecs::push_delete::<MoveToTargetComponent>(info, &mut updates);
@@ -55,25 +115,210 @@ impl System for MoveToTargetSystem {
             }
         }
 
+        for (info, offset_x, offset_y) in offsets_moved {
+            let distance = (offset_x.powi(2) + offset_y.powi(2)).sqrt();
+
+            // Moving makes creatures tired
+            if let Some(creature) = ecs.component_mut::<CreatureComponent>(&info) {
+                creature.fatigue = f32::min(
+                    creature.fatigue + distance as f32 * config.creature.fatigue_per_distance,
+                    config.creature.max_fatigue,
+                );
+            }
+
+            // Creatures look where they go
+            if distance > 0.0
+                && let Some(perception) = ecs.component_mut::<PerceptionComponent>(&info)
+            {
+                perception.heading = offset_y.atan2(offset_x);
+            }
+
+            // The heading followed by the neighbours
+            if let Some(herd) = ecs.component_mut::<HerdComponent>(&info) {
+                herd.heading = if distance > 0.0 {
+                    (offset_x / distance, offset_y / distance)
+                } else {
+                    (0.0, 0.0)
+                };
+            }
+        }
+
         ecs.apply(updates);
     }
 }
 
+#[derive(Clone, Copy)]
+struct HerdMember {
+    species: usize,
+    position: (f64, f64),
+    heading: (f64, f64),
+}
+
+/// Steering of a herd member by its neighbours: pushed away by the closest ones (separation),
+/// turned towards their average heading (alignment) and pulled towards their center (cohesion)
+fn herd_steering(
+    position: (f64, f64),
+    neighbours: &[HerdMember],
+    config: &HerdConfig,
+) -> (f64, f64) {
+    if neighbours.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let mut separation = (0.0, 0.0);
+    let mut alignment = (0.0, 0.0);
+    let mut center = (0.0, 0.0);
+    for neighbour in neighbours {
+        let (dx, dy) = (
+            position.0 - neighbour.position.0,
+            position.1 - neighbour.position.1,
+        );
+        let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+        // The closer the neighbour, the stronger the push
+        if distance > 0.0 && distance < config.separation_distance {
+            let strength = (config.separation_distance - distance) / config.separation_distance;
+            separation.0 += dx / distance * strength;
+            separation.1 += dy / distance * strength;
+        }
+        alignment.0 += neighbour.heading.0;
+        alignment.1 += neighbour.heading.1;
+        center.0 += neighbour.position.0;
+        center.1 += neighbour.position.1;
+    }
+    let nb = neighbours.len() as f64;
+    alignment = (alignment.0 / nb, alignment.1 / nb);
+
+    // The pull grows with the distance to the center, up to the radius of the herd
+    let (dx, dy) = (center.0 / nb - position.0, center.1 / nb - position.1);
+    let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+    let cohesion = if distance > 0.0 {
+        let strength = f64::min(distance / config.radius, 1.0);
+        (dx / distance * strength, dy / distance * strength)
+    } else {
+        (0.0, 0.0)
+    };
+
+    (
+        separation.0 * config.separation_weight
+            + alignment.0 * config.alignment_weight
+            + cohesion.0 * config.cohesion_weight,
+        separation.1 * config.separation_weight
+            + alignment.1 * config.alignment_weight
+            + cohesion.1 * config.cohesion_weight,
+    )
+}
+
+/// Chase a moving target. When nothing stands between the pursuer and the point where it can
+/// intercept the target, the pursuer goes straight to it. Otherwise it follows its path, computed
+/// again when the target has strayed too far from its end.
+/// Return None if the move is left to the path following.
+fn pursue(
+    config: &Config,
+    body: &mut BodyComponent,
+    move_to_target: &mut MoveToTargetComponent,
+    speed: f64,
+    info: &EntityInfo,
+) -> Option<MoveToTargetResult> {
+    let pursuit = *move_to_target.pursuit()?;
+    if pursuit.chase_ticks > config.pursuit.max_chase_ticks {
+        // Give up
+        return Some(MoveToTargetResult::Stopped);
+    }
+
+    let target_body = *move_to_target.target_body();
+    let (x, y) = intercept_point(
+        (body.x(), body.y()),
+        (target_body.x(), target_body.y()),
+        pursuit.velocity,
+        speed,
+        config.pursuit.max_prediction_ticks,
+    );
+    let (dx, dy) = (x - body.x(), y - body.y());
+    let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+    if distance > 0.0
+        && !body_grid::edge_collides(
+            (body.x(), body.y()),
+            (x, y),
+            info.entity,
+            move_to_target.target_entity(),
+            (body.w(), body.h()),
+        )
+    {
+        let step = f64::min(speed, distance);
+        if body.try_translate(info.entity, dx / distance * step, dy / distance * step) {
+            return Some(MoveToTargetResult::Moved);
+        }
+    }
+
+    let strayed = move_to_target.path_end().is_none_or(|(end_x, end_y)| {
+        (end_x - target_body.x()).powi(2) + (end_y - target_body.y()).powi(2)
+            > config.pursuit.replan_distance.powi(2)
+    });
+    if (strayed || move_to_target.is_last_waypoint_reached())
+        && !move_to_target.compute_path(config, info.entity, body)
+    {
+        return Some(MoveToTargetResult::Stopped);
+    }
+    None
+}
+
+/// Point where a pursuer moving at the given speed can meet a target moving at a constant
+/// velocity. The prediction is limited to the given number of ticks ahead.
+fn intercept_point(
+    position: (f64, f64),
+    target: (f64, f64),
+    velocity: (f64, f64),
+    speed: f64,
+    max_prediction_ticks: f64,
+) -> (f64, f64) {
+    // Solve |target + velocity * t - position| = speed * t
+    let (rx, ry) = (target.0 - position.0, target.1 - position.1);
+    let a = velocity.0.powi(2) + velocity.1.powi(2) - speed.powi(2);
+    let b = 2.0 * (rx * velocity.0 + ry * velocity.1);
+    let c = rx.powi(2) + ry.powi(2);
+    let t = if a.abs() < f64::EPSILON {
+        if b < 0.0 {
+            -c / b
+        } else {
+            max_prediction_ticks
+        }
+    } else {
+        let discriminant = b.powi(2) - 4.0 * a * c;
+        if discriminant < 0.0 {
+            // The target cannot be caught up
+            max_prediction_ticks
+        } else {
+            let sqrt = discriminant.sqrt();
+            [(-b - sqrt) / (2.0 * a), (-b + sqrt) / (2.0 * a)]
+                .into_iter()
+                .filter(|t| *t >= 0.0)
+                .fold(max_prediction_ticks, f64::min)
+        }
+    };
+    let t = t.min(max_prediction_ticks);
+    (target.0 + velocity.0 * t, target.1 + velocity.1 * t)
+}
+
 fn try_move(
     config: &Config,
     body: &mut BodyComponent,
     move_to_target: &mut MoveToTargetComponent,
+    speed: f64,
+    steering: Option<(f64, f64)>,
     info: &EntityInfo,
     target_bodies: &HashMap<EntityId, Option<BodyComponent>>,
 ) -> MoveToTargetResult {
-    // Get the target position, if possible (the entity could have been deleted)
-    let target_body;
-    if let Some(b) = target_bodies[&move_to_target.target_entity()] {
-        target_body = b;
-    } else {
-        return MoveToTargetResult::Stopped;
+    // Get the target position, if possible (the entity could have been deleted).
+    // Without target entity, the target is a fixed position.
+    if move_to_target.target_entity() != RESERVED_ENTITY_ID {
+        let target_body;
+        if let Some(b) = target_bodies[&move_to_target.target_entity()] {
+            target_body = b;
+        } else {
+            return MoveToTargetResult::Stopped;
+        }
+        move_to_target.update_target(target_body);
     }
-    *move_to_target.target_body_mut() = target_body;
 
     // Check if the target is already reached (in which case there is no need to go through the path)
     if body.almost_collides(
@@ -84,10 +329,16 @@ fn try_move(
         return MoveToTargetResult::Reached;
     }
 
+    if move_to_target.pursuit().is_some()
+        && let Some(result) = pursue(config, body, move_to_target, speed, info)
+    {
+        return result;
+    }
+
     let (waypoint_x, waypoint_y) = move_to_target.next_waypoint().unwrap();
 
     // Check if the next waypoint is reached
-    if body.almost_at_position(waypoint_x, waypoint_y, move_to_target.speed()) {
+    if body.almost_at_position(waypoint_x, waypoint_y, speed) {
         move_to_target.waypoint_reached();
         // If the path is finished, but target not reached, it's a failure
         if move_to_target.is_last_waypoint_reached() {
@@ -98,8 +349,27 @@ fn try_move(
     else {
         let vec_to_target = (waypoint_x - body.x(), waypoint_y - body.y());
         let norm = (vec_to_target.0.powi(2) + vec_to_target.1.powi(2)).sqrt();
-        let offset_x = vec_to_target.0 / norm * move_to_target.speed();
-        let offset_y = vec_to_target.1 / norm * move_to_target.speed();
+        let direction = (vec_to_target.0 / norm, vec_to_target.1 / norm);
+
+        // Deviate from the path with the herd, as long as the move still gets closer to the
+        // waypoint. If the deviated move collides, fall back to the move along the path.
+        if let Some((steering_x, steering_y)) = steering {
+            let steered = (direction.0 + steering_x, direction.1 + steering_y);
+            let steered_norm = (steered.0.powi(2) + steered.1.powi(2)).sqrt();
+            if steered_norm > 0.0
+                && steered.0 * direction.0 + steered.1 * direction.1 > 0.0
+                && body.try_translate(
+                    info.entity,
+                    steered.0 / steered_norm * speed,
+                    steered.1 / steered_norm * speed,
+                )
+            {
+                return MoveToTargetResult::Moved;
+            }
+        }
+
+        let offset_x = direction.0 * speed;
+        let offset_y = direction.1 * speed;
         if body.try_translate(info.entity, offset_x, offset_y) {
             return MoveToTargetResult::Moved;
         } else {
@@ -111,3 +381,73 @@ fn try_move(
     }
     MoveToTargetResult::Moved
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(x: f64, y: f64, heading: (f64, f64)) -> HerdMember {
+        HerdMember {
+            species: 0,
+            position: (x, y),
+            heading,
+        }
+    }
+
+    #[test]
+    fn test_herd_steering() {
+        let config = HerdConfig {
+            radius: 100.0,
+            separation_distance: 20.0,
+            separation_weight: 1.0,
+            alignment_weight: 0.0,
+            cohesion_weight: 0.0,
+        };
+        // No neighbour, no steering
+        assert_eq!(herd_steering((0.0, 0.0), &[], &config), (0.0, 0.0));
+
+        // A neighbour too close on the right pushes to the left
+        let (x, y) = herd_steering((0.0, 0.0), &[member(10.0, 0.0, (0.0, 0.0))], &config);
+        assert!(x < 0.0 && y == 0.0);
+
+        // A distant neighbour on the right pulls to the right
+        let config = HerdConfig {
+            separation_weight: 0.0,
+            cohesion_weight: 1.0,
+            ..config
+        };
+        let (x, y) = herd_steering((0.0, 0.0), &[member(50.0, 0.0, (0.0, 0.0))], &config);
+        assert!(x > 0.0 && y == 0.0);
+
+        // Neighbours heading down steer down
+        let config = HerdConfig {
+            cohesion_weight: 0.0,
+            alignment_weight: 1.0,
+            ..config
+        };
+        let neighbours = [
+            member(50.0, 0.0, (0.0, 1.0)),
+            member(-50.0, 0.0, (0.0, 1.0)),
+        ];
+        assert_eq!(herd_steering((0.0, 0.0), &neighbours, &config), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_intercept_point() {
+        // A motionless target is met where it stands
+        assert_eq!(
+            intercept_point((0.0, 0.0), (10.0, 0.0), (0.0, 0.0), 1.0, 100.0),
+            (10.0, 0.0)
+        );
+
+        // A target crossing in front of the pursuer is met after 10 ticks
+        let (x, y) = intercept_point((0.0, 0.0), (8.0, 0.0), (0.0, 0.6), 1.0, 100.0);
+        assert!((x - 8.0).abs() < 1e-9 && (y - 6.0).abs() < 1e-9);
+
+        // A target fleeing faster than the pursuer is anticipated up to the prediction limit
+        assert_eq!(
+            intercept_point((0.0, 0.0), (10.0, 0.0), (2.0, 0.0), 1.0, 5.0),
+            (20.0, 0.0)
+        );
+    }
+}
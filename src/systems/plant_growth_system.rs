@@ -4,11 +4,20 @@ use crate::configuration::Config;
 use crate::ecs::{Ecs, System, iter_components};
 use crate::humidity;
 use crate::shared_data::body_grid;
+use crate::shared_data::climate;
+use crate::shared_data::soil;
 use std::any::TypeId;
 
 pub struct PlantGrowthSystem;
 impl System for PlantGrowthSystem {
     fn run(&mut self, ecs: &mut Ecs, config: &Config) {
+        soil::regenerate();
+
+        // The humidity, the growth and the seed production change with the seasons
+        let climate = climate::current();
+        let humidity =
+            |x: f64, y: f64| (humidity(x, y) * climate.humidity_factor()).clamp(0.0, 1.0);
+
         for (plant, body, info) in iter_components!(ecs, (), (PlantComponent, BodyComponent)) {
             // Initialize seeds with humidity level
             if !plant.is_seed_initialized {
@@ -37,14 +46,26 @@ impl System for PlantGrowthSystem {
                 }
             }
 
-            // Grow plant, if there is enough space
-            let new_size = (plant.size + plant.growth_per_tick).min(plant.max_size);
-            if new_size != plant.size && body.try_update_size(info.entity, new_size, new_size) {
+            // Grow plant, faster and bigger on a rich soil, if there is enough space.
+            // Growing consumes the nutrients of the soil.
+            let (x, y) = (body.x(), body.y());
+            let nutrient_factor = (soil::nutrients(x, y) / config.soil.base_nutrients)
+                .min(config.soil.max_growth_factor);
+            let growth = plant.growth_per_tick * nutrient_factor * climate.growth_factor();
+            let new_size = (plant.size + growth).min(plant.max_size * nutrient_factor);
+            if new_size > plant.size && body.try_update_size(info.entity, new_size, new_size) {
+                soil::consume(
+                    x,
+                    y,
+                    (new_size - plant.size) * config.soil.nutrients_per_size,
+                );
                 plant.size = new_size;
             }
 
             // Grow new seeds
-            if plant.count_ticks_to_seed >= plant.ticks_per_seed {
+            if plant.count_ticks_to_seed as f64
+                >= plant.ticks_per_seed as f64 / climate.seed_factor()
+            {
                 plant.count_ticks_to_seed = 0;
                 plant.nb_seeds = (plant.nb_seeds + 1).min(plant.max_nb_seeds);
             }
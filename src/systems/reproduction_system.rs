@@ -1,91 +1,87 @@
+use crate::components::age_component::AgeComponent;
 use crate::components::agent_component::AgentComponent;
 use crate::components::all::*;
 use crate::components::body_component::BodyComponent;
+use crate::components::genome_component::GenomeComponent;
 use crate::configuration::Config;
-use crate::ecs::{Component, Ecs, RESERVED_ENTITY_ID, System, Update, iter_entities, to_ctype};
+use crate::ecs::{Ecs, RESERVED_ENTITY_ID, System, Update, iter_entities};
+use crate::shared_data::biome;
 use crate::shared_data::body_grid;
+use crate::systems::utils;
 use std::any::TypeId;
 
-pub struct ReproductionSystem {
-    herbivorous_goal_set: usize,
-    herbivorous_action_set: usize,
-    herbivorous_action_set_len: usize,
-    carnivorous_goal_set: usize,
-    carnivorous_action_set: usize,
-    carnivorous_action_set_len: usize,
-}
-impl ReproductionSystem {
-    pub fn new(
-        herbivorous_goal_set: usize,
-        herbivorous_action_set: usize,
-        herbivorous_action_set_len: usize,
-        carnivorous_goal_set: usize,
-        carnivorous_action_set: usize,
-        carnivorous_action_set_len: usize,
-    ) -> Self {
-        Self {
-            herbivorous_goal_set,
-            herbivorous_action_set,
-            herbivorous_action_set_len,
-            carnivorous_goal_set,
-            carnivorous_action_set,
-            carnivorous_action_set_len,
-        }
-    }
-}
+pub struct ReproductionSystem;
 impl System for ReproductionSystem {
     fn run(&mut self, ecs: &mut Ecs, config: &Config) {
         let mut updates: Vec<Update> = Vec::new();
 
         // Find creatures that can reproduce
-        for info in iter_entities!(ecs, CreatureComponent, BodyComponent) {
-            // Check if the creature has enough energy to reproduce
+        for info in iter_entities!(
+            ecs,
+            CreatureComponent,
+            BodyComponent,
+            SpeciesComponent,
+            AgentComponent
+        ) {
+            let species = *ecs.component::<SpeciesComponent>(&info).unwrap();
+            let base_genome = GenomeComponent::new(&config.creature, species.config(config));
+            let parent_genome = ecs
+                .component::<GenomeComponent>(&info)
+                .copied()
+                .unwrap_or(base_genome);
+
+            // Check if the creature is an adult with enough energy to reproduce
+            if !utils::can_reproduce(ecs, &info) {
+                continue;
+            }
             {
                 let creature = ecs.component_mut::<CreatureComponent>(&info).unwrap();
-                if creature.energy < config.creature.reprod_energy_threshold {
+                if creature.energy < parent_genome.reprod_energy_threshold {
                     continue;
                 }
             }
 
+            let genome = parent_genome.offspring(
+                &parent_genome,
+                &base_genome,
+                &config.genome,
+                (
+                    config.creature.reprod_energy_cost,
+                    config.creature.max_energy,
+                ),
+            );
+            let size = genome.size * config.aging.newborn_size_factor;
             let body = ecs.component::<BodyComponent>(&info).unwrap();
             let new_body = BodyComponent::new_not_traversable(
-                body.x() + config.creature.size + config.creature.reprod_x_offset,
+                body.x() + (body.w() + size) / 2.0 + config.creature.reprod_x_offset,
                 body.y(),
-                config.creature.size,
-                config.creature.size,
+                size,
+                size,
             );
 
-            // Reproduce only if there is a free space for the new creature
-            if body_grid::collides(RESERVED_ENTITY_ID, &new_body) {
+            // Reproduce only if there is a free space on land for the new creature
+            if biome::is_water(new_body.x(), new_body.y())
+                || body_grid::collides(RESERVED_ENTITY_ID, &new_body)
+            {
                 continue;
             }
 
-            let mut comps: Vec<Box<dyn Component>> = vec![
-                Box::new(CreatureComponent::new(&config.creature)),
-                Box::new(new_body),
-            ];
-
-            // Check if herbivorous or carnivorous
-            let is_herbivorous =
-                ecs.has_component(info.arch_index, &to_ctype!(HerbivorousComponent));
-            if is_herbivorous {
-                comps.push(Box::new(HerbivorousComponent::new()));
-                comps.push(Box::new(AgentComponent::new(
-                    self.herbivorous_goal_set,
-                    self.herbivorous_action_set,
-                    self.herbivorous_action_set_len,
-                )));
-            } else {
-                comps.push(Box::new(CarnivorousComponent::new()));
-                comps.push(Box::new(AgentComponent::new(
-                    self.carnivorous_goal_set,
-                    self.carnivorous_action_set,
-                    self.carnivorous_action_set_len,
-                )));
+            // The offspring has the same goals and actions as its parent
+            let parent = ecs.component::<AgentComponent>(&info).unwrap();
+            let mut agent = parent.offspring();
+            if config.agent.inherit_action_stats {
+                agent.inherit_action_stats(parent);
             }
 
             // Create a new creature
-            updates.push(Update::Create(comps));
+            updates.push(Update::Create(utils::creature_components(
+                config,
+                species,
+                genome,
+                new_body,
+                AgeComponent::new(species.config(config).lifespan, &config.aging),
+                agent,
+            )));
 
             // Apply reproduction energy cost to parent creature
             {
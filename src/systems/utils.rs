@@ -1,42 +1,90 @@
 use crate::algorithms::path_finding::WayPoint;
 use crate::algorithms::path_finding::compute_path;
-use crate::components::all::PlantComponent;
+use crate::components::age_component::AgeComponent;
+use crate::components::agent_component::AgentComponent;
+use crate::components::all::{
+    CreatureComponent, DigestionComponent, HerdComponent, PerceptionComponent, PlantComponent,
+    SleepingComponent, SpeciesComponent,
+};
 use crate::components::body_component::BodyComponent;
-use crate::configuration::Config;
+use crate::components::genome_component::GenomeComponent;
+use crate::components::inbox_component::{InboxComponent, Signal};
+use crate::components::memory_component::{MemoryComponent, MemoryKind};
+use crate::configuration::{Activity, Config, CreatureConfig};
 use crate::ecs::iter_components;
 use crate::ecs::to_ctype;
-use crate::ecs::{Component, Ecs, EntityId};
+use crate::ecs::{Component, Ecs, EntityId, EntityInfo, RESERVED_ENTITY_ID};
+use crate::shared_data::biome;
 use crate::shared_data::body_grid;
+use crate::shared_data::climate;
 use std::any::TypeId;
 use std::collections::HashSet;
+use std::f64::consts::PI;
 
-// If an empty path is returned, it means that the target is already reached
-pub fn find_closest_reachable<C>(
+// Deviations from the direction opposite to the threats tried to find a flee position, in order
+const FLEE_ANGLES: [f64; 7] = [
+    0.0,
+    PI / 6.0,
+    -PI / 6.0,
+    PI / 3.0,
+    -PI / 3.0,
+    PI / 2.0,
+    -PI / 2.0,
+];
+
+// Directions tried around the parents to place their offspring, in order
+const OFFSPRING_ANGLES: [f64; 8] = [
+    0.0,
+    PI,
+    PI / 2.0,
+    -PI / 2.0,
+    PI / 4.0,
+    -PI / 4.0,
+    3.0 * PI / 4.0,
+    -3.0 * PI / 4.0,
+];
+
+// If an empty path is returned, it means that the target is already reached.
+// Only the targets seen by the creature are considered, and the ones rejected by the filter are
+// ignored.
+pub fn find_closest_reachable<C, F>(
     ecs: &mut Ecs,
     config: &Config,
-    entity: EntityId,
+    info: &EntityInfo,
     body: &BodyComponent,
+    is_valid_target: F,
 ) -> Option<(f64, EntityId, BodyComponent, Vec<WayPoint>)>
 where
     C: Component,
+    F: Fn(&Ecs, &EntityInfo) -> bool,
 {
-    for (target_entity, distance_squared) in
-        body_grid::iter_closest(entity, body, config.path.max_search_distance)
+    let entity = info.entity;
+    let search_distance =
+        perception_distance(ecs, info, &config.creature).min(config.path.max_search_distance);
+    for (target_entity, distance_squared) in body_grid::iter_closest(entity, body, search_distance)
     {
-        if let Some(info) = ecs.get_entity_info(target_entity)
+        if let Some(t_info) = ecs.get_entity_info(target_entity)
             && ecs.has_components(
-                info.arch_index,
+                t_info.arch_index,
                 &HashSet::from([to_ctype!(C), to_ctype!(BodyComponent)]),
             )
+            && is_valid_target(ecs, &t_info)
+            && is_visible(
+                ecs,
+                info,
+                body,
+                target_entity,
+                ecs.component::<BodyComponent>(&t_info).unwrap(),
+            )
         {
             // If the target is a plant, check if it is eatable
-            if let Some(target_plant) = ecs.component::<PlantComponent>(&info)
+            if let Some(target_plant) = ecs.component::<PlantComponent>(&t_info)
                 && !target_plant.is_eatable()
             {
                 continue;
             }
 
-            let target_body = ecs.component::<BodyComponent>(&info).unwrap();
+            let target_body = ecs.component::<BodyComponent>(&t_info).unwrap();
 
             // Check if the target is already reached
             if body.almost_collides(target_body, config.collision.contact_center_2_center_factor) {
@@ -53,6 +101,55 @@ where
     None
 }
 
+/// Find the closest remembered entity of the given kind (having the component C) whose remembered
+/// position is still reachable, avoiding the ones remembered close to a predator.
+/// The body returned is at the remembered position (the entity may have moved since).
+/// The memories of the entities that no longer exist are forgotten.
+pub fn find_closest_remembered<C>(
+    ecs: &mut Ecs,
+    config: &Config,
+    info: &EntityInfo,
+    body: &BodyComponent,
+    kind: MemoryKind,
+) -> Option<(EntityId, BodyComponent, Vec<WayPoint>)>
+where
+    C: Component,
+{
+    let memory = ecs.component::<MemoryComponent>(info)?;
+    let remembered: Vec<(EntityId, (f64, f64))> = memory
+        .recall(kind, (body.x(), body.y()))
+        .into_iter()
+        .filter(|e| !memory.is_dangerous((e.x, e.y), &config.memory))
+        .map(|e| (e.entity, (e.x, e.y)))
+        .collect();
+
+    let mut forgotten = Vec::new();
+    let mut found = None;
+    for (target_entity, (x, y)) in remembered {
+        let target_body = ecs.get_entity_info(target_entity).and_then(|target_info| {
+            ecs.component::<C>(&target_info)?;
+            ecs.component::<BodyComponent>(&target_info).copied()
+        });
+        let Some(target_body) = target_body else {
+            forgotten.push(target_entity);
+            continue;
+        };
+        let target_body = target_body.clone_translated(x - target_body.x(), y - target_body.y());
+        if let Some((path, _)) =
+            compute_path(config, info.entity, body, target_entity, &target_body)
+        {
+            found = Some((target_entity, target_body, path));
+            break;
+        }
+    }
+
+    let memory = ecs.component_mut::<MemoryComponent>(info)?;
+    for entity in forgotten {
+        memory.forget(entity);
+    }
+    found
+}
+
 #[allow(dead_code)]
 pub fn find_closest<C>(
     ecs: &mut Ecs,
@@ -72,3 +169,357 @@ where
     }
     opt_entity
 }
+
+/// Distance under which a creature sees other entities (given by its genome, reduced in the
+/// dark and while sleeping)
+pub fn perception_distance(ecs: &Ecs, info: &EntityInfo, config: &CreatureConfig) -> f64 {
+    let night_vision = ecs
+        .component::<PerceptionComponent>(info)
+        .is_some_and(|perception| perception.night_vision);
+    let perception_radius = ecs
+        .component::<GenomeComponent>(info)
+        .map_or(config.perception_radius, |genome| genome.perception_radius)
+        * climate::current().perception_factor(night_vision);
+    if ecs.has_component(info.arch_index, &to_ctype!(SleepingComponent)) {
+        perception_radius * config.sleep_perception_factor
+    } else {
+        perception_radius
+    }
+}
+
+/// Check if a creature sees the target: the target must be within its field of view, and not
+/// hidden behind another body (the distance is not checked)
+pub fn is_visible(
+    ecs: &Ecs,
+    info: &EntityInfo,
+    body: &BodyComponent,
+    target_entity: EntityId,
+    target_body: &BodyComponent,
+) -> bool {
+    if let Some(perception) = ecs.component::<PerceptionComponent>(info)
+        && let Some(genome) = ecs.component::<GenomeComponent>(info)
+        && !perception.faces(
+            (target_body.x() - body.x(), target_body.y() - body.y()),
+            genome.field_of_view,
+        )
+    {
+        return false;
+    }
+    !body_grid::edge_collides(
+        (body.x(), body.y()),
+        (target_body.x(), target_body.y()),
+        info.entity,
+        target_entity,
+        (0.0, 0.0),
+    )
+}
+
+/// Return the entities having the component C seen by the creature, sorted by distance
+/// (as euclidian distance squared)
+pub fn find_visible<C>(
+    ecs: &Ecs,
+    info: &EntityInfo,
+    body: &BodyComponent,
+    config: &CreatureConfig,
+) -> Vec<(f64, EntityId, BodyComponent)>
+where
+    C: Component,
+{
+    find_all_in_range::<C>(
+        ecs,
+        info.entity,
+        body,
+        perception_distance(ecs, info, config),
+    )
+    .into_iter()
+    .filter(|(_, target_entity, target_body)| {
+        is_visible(ecs, info, body, *target_entity, target_body)
+    })
+    .collect()
+}
+
+/// Speed of a creature, given by its genome (or by its species) and its age
+pub fn creature_speed(ecs: &Ecs, info: &EntityInfo, config: &Config) -> f64 {
+    let speed = if let Some(genome) = ecs.component::<GenomeComponent>(info) {
+        genome.speed
+    } else {
+        ecs.component::<SpeciesComponent>(info)
+            .map_or(0.0, |species| species.config(config).speed)
+    };
+    ecs.component::<AgeComponent>(info)
+        .map_or(speed, |age| speed * age.speed_factor(&config.aging))
+}
+
+/// Check if the creature is old enough (and not too old) to reproduce
+pub fn can_reproduce(ecs: &Ecs, info: &EntityInfo) -> bool {
+    ecs.component::<AgeComponent>(info)
+        .is_none_or(|age| age.can_reproduce())
+}
+
+/// Return the species hunting the creature
+pub fn predators_of(ecs: &Ecs, info: &EntityInfo, config: &Config) -> Vec<usize> {
+    ecs.component::<SpeciesComponent>(info)
+        .map_or(Vec::new(), |species| config.predators_of(species.species))
+}
+
+/// Return the species hunted by the creature's species (none if it has no species)
+pub fn prey_of(ecs: &Ecs, info: &EntityInfo, config: &Config) -> Vec<usize> {
+    ecs.component::<SpeciesComponent>(info)
+        .map_or(Vec::new(), |species| config.prey_of(species.species))
+}
+
+/// Return the creatures of the given species seen by the creature, sorted by distance
+/// (as euclidian distance squared)
+pub fn find_visible_species(
+    ecs: &Ecs,
+    info: &EntityInfo,
+    body: &BodyComponent,
+    species: &[usize],
+    config: &CreatureConfig,
+) -> Vec<(f64, EntityId, BodyComponent)> {
+    if species.is_empty() {
+        return Vec::new();
+    }
+    find_visible::<SpeciesComponent>(ecs, info, body, config)
+        .into_iter()
+        .filter(|(_, target_entity, _)| {
+            ecs.component_from_entity::<SpeciesComponent>(*target_entity)
+                .is_some_and(|s| species.contains(&s.species))
+        })
+        .collect()
+}
+
+/// Return the threats known by the creature within its perception distance, sorted by distance
+/// (as euclidian distance squared): the predators it sees, the ones reported by alarm calls, and
+/// the creature that wounded it
+pub fn known_threats(
+    ecs: &Ecs,
+    info: &EntityInfo,
+    body: &BodyComponent,
+    predators: &[usize],
+    config: &CreatureConfig,
+) -> Vec<(f64, BodyComponent)> {
+    let seen = find_visible_species(ecs, info, body, predators, config);
+    let max_distance_squared = perception_distance(ecs, info, config).powi(2);
+    let mut threats: Vec<(f64, BodyComponent)> = ecs
+        .component::<InboxComponent>(info)
+        .into_iter()
+        .flat_map(|inbox| inbox.messages(Signal::Alarm))
+        .filter(|m| !seen.iter().any(|(_, entity, _)| *entity == m.subject))
+        .map(|m| {
+            (
+                (m.x - body.x()).powi(2) + (m.y - body.y()).powi(2),
+                BodyComponent::new_traversable(m.x, m.y, 0.0, 0.0),
+            )
+        })
+        .filter(|(distance_squared, _)| *distance_squared < max_distance_squared)
+        .collect();
+    if let Some(attacker) = ecs
+        .component::<CreatureComponent>(info)
+        .and_then(|creature| creature.last_attacker)
+        && ecs
+            .component_from_entity::<SpeciesComponent>(attacker)
+            .is_some_and(|s| predators.contains(&s.species))
+        && !seen.iter().any(|(_, entity, _)| *entity == attacker)
+        && let Some(attacker_body) = ecs.component_from_entity::<BodyComponent>(attacker)
+    {
+        let distance_squared =
+            (attacker_body.x() - body.x()).powi(2) + (attacker_body.y() - body.y()).powi(2);
+        if distance_squared < max_distance_squared {
+            threats.push((distance_squared, *attacker_body));
+        }
+    }
+    threats.extend(seen.into_iter().map(|(d, _, threat_body)| (d, threat_body)));
+    threats.sort_by(|a, b| a.0.total_cmp(&b.0));
+    threats
+}
+
+/// Components of a new creature
+pub fn creature_components(
+    config: &Config,
+    species: SpeciesComponent,
+    genome: GenomeComponent,
+    body: BodyComponent,
+    age: AgeComponent,
+    agent: AgentComponent,
+) -> Vec<Box<dyn Component>> {
+    let mut components: Vec<Box<dyn Component>> = vec![
+        Box::new(CreatureComponent::new(&config.creature)),
+        Box::new(body),
+        Box::new(genome),
+        Box::new(species),
+        Box::new(age),
+        Box::new(DigestionComponent::new()),
+        Box::new(MemoryComponent::new()),
+        Box::new(InboxComponent::new()),
+        Box::new(PerceptionComponent::new(
+            species.config(config).activity == Activity::Nocturnal,
+        )),
+        Box::new(agent),
+    ];
+    if species.config(config).herd.is_some() {
+        components.push(Box::new(HerdComponent::new()));
+    }
+    components
+}
+
+/// Return all the entities having the component C within the distance, sorted by distance
+/// (as euclidian distance squared)
+pub fn find_all_in_range<C>(
+    ecs: &Ecs,
+    entity: EntityId,
+    body: &BodyComponent,
+    max_distance: f64,
+) -> Vec<(f64, EntityId, BodyComponent)>
+where
+    C: Component,
+{
+    let mut found: Vec<(f64, EntityId, BodyComponent)> =
+        body_grid::iter_closest(entity, body, max_distance)
+            .filter_map(|(target_entity, distance_squared)| {
+                let info = ecs.get_entity_info(target_entity)?;
+                if !ecs.has_component(info.arch_index, &to_ctype!(C)) {
+                    return None;
+                }
+                let target_body = ecs.component::<BodyComponent>(&info)?;
+                Some((distance_squared, target_entity, *target_body))
+            })
+            .collect();
+    found.sort_by(|a, b| a.0.total_cmp(&b.0));
+    found
+}
+
+/// Find a free position on land for an offspring around the middle of its parents
+pub fn find_offspring_position(
+    parent: &BodyComponent,
+    other_parent: &BodyComponent,
+    size: f64,
+    distance: f64,
+) -> Option<BodyComponent> {
+    let x = (parent.x() + other_parent.x()) / 2.0;
+    let y = (parent.y() + other_parent.y()) / 2.0;
+    OFFSPRING_ANGLES.iter().find_map(|a| {
+        let position = BodyComponent::new_not_traversable(
+            x + a.cos() * distance,
+            y + a.sin() * distance,
+            size,
+            size,
+        );
+        (!biome::is_water(position.x(), position.y())
+            && !body_grid::collides(RESERVED_ENTITY_ID, &position))
+        .then_some(position)
+    })
+}
+
+/// Find a reachable position away from the threats, at the given distance.
+/// Return the position (as a body of the same size as the fleeing entity) and the path to it.
+pub fn find_flee_position(
+    config: &Config,
+    entity: EntityId,
+    body: &BodyComponent,
+    threats: &[BodyComponent],
+    flee_distance: f64,
+) -> Option<(BodyComponent, Vec<WayPoint>)> {
+    // Direction opposite to the threats, the closest ones weighing more
+    let (mut dir_x, mut dir_y) = (0.0, 0.0);
+    for threat in threats {
+        let (dx, dy) = (body.x() - threat.x(), body.y() - threat.y());
+        let distance_squared = (dx.powi(2) + dy.powi(2)).max(1.0);
+        dir_x += dx / distance_squared;
+        dir_y += dy / distance_squared;
+    }
+    let angle = if dir_x == 0.0 && dir_y == 0.0 {
+        0.0
+    } else {
+        dir_y.atan2(dir_x)
+    };
+
+    for deviation in FLEE_ANGLES {
+        let a = angle + deviation;
+        let position = BodyComponent::new_not_traversable(
+            body.x() + a.cos() * flee_distance,
+            body.y() + a.sin() * flee_distance,
+            body.w(),
+            body.h(),
+        );
+        if biome::is_water(position.x(), position.y()) || body_grid::collides(entity, &position) {
+            continue;
+        }
+        if let Some((path, _)) = compute_path(config, entity, body, RESERVED_ENTITY_ID, &position) {
+            return Some((position, path));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        components::{
+            all::{CorpseComponent, ObstacleComponent, PerceptionComponent},
+            body_component::BodyComponent,
+            genome_component::GenomeComponent,
+        },
+        ecs::{Ecs, EntityInfo, Update, iter_entities},
+        shared_data::body_grid,
+        systems::utils::is_visible,
+    };
+    use std::{any::TypeId, f64::consts::PI};
+
+    #[test]
+    fn test_occlusion() {
+        body_grid::init_with_area((-500.0, -500.0), (500.0, 500.0), 100.0);
+        let mut ecs = Ecs::new();
+        let genome = GenomeComponent {
+            speed: 1.0,
+            size: 10.0,
+            hunger_rate: 0.0,
+            perception_radius: 300.0,
+            field_of_view: PI,
+            reprod_energy_threshold: 0.0,
+        };
+        let perception = PerceptionComponent {
+            heading: 0.0,
+            night_vision: false,
+        };
+        ecs.apply(vec![
+            Update::Create(vec![
+                Box::new(BodyComponent::new_not_traversable(0.0, 0.0, 10.0, 10.0)),
+                Box::new(genome),
+                Box::new(perception),
+            ]),
+            Update::Create(vec![
+                Box::new(BodyComponent::new_not_traversable(100.0, 0.0, 20.0, 20.0)),
+                Box::new(ObstacleComponent::new()),
+            ]),
+            Update::Create(vec![
+                Box::new(BodyComponent::new_not_traversable(200.0, 0.0, 10.0, 10.0)),
+                Box::new(CorpseComponent::new(0.0)),
+            ]),
+        ]);
+        let info: EntityInfo = iter_entities!(ecs, PerceptionComponent).next().unwrap();
+        let body = *ecs.component::<BodyComponent>(&info).unwrap();
+        let obstacle = iter_entities!(ecs, ObstacleComponent).next().unwrap();
+        let obstacle_body = *ecs.component::<BodyComponent>(&obstacle).unwrap();
+        let target = iter_entities!(ecs, CorpseComponent).next().unwrap();
+        let target_body = *ecs.component::<BodyComponent>(&target).unwrap();
+
+        // The obstacle in front is seen, and hides the target behind it
+        assert!(is_visible(
+            &ecs,
+            &info,
+            &body,
+            obstacle.entity,
+            &obstacle_body
+        ));
+        assert!(!is_visible(&ecs, &info, &body, target.entity, &target_body));
+
+        // Once the obstacle is gone, the target is seen
+        ecs.apply(vec![Update::DeleteEntity(obstacle)]);
+        assert!(is_visible(&ecs, &info, &body, target.entity, &target_body));
+
+        // But not behind the creature
+        let behind = target_body.clone_translated(-400.0, 0.0);
+        assert!(!is_visible(&ecs, &info, &body, target.entity, &behind));
+    }
+}